@@ -0,0 +1,976 @@
+use core::cmp::Reverse;
+use std::collections::HashMap;
+
+use bevy_app::{App, FixedUpdate, Plugin, PostUpdate};
+use bevy_ecs::{
+    entity::EntityHashMap,
+    entity_disabling::Disabled,
+    hierarchy::ChildOf,
+    prelude::{
+        Commands, Component, DetectChangesMut, Entity, Event, EventWriter, Has,
+        IntoScheduleConfigs, Local, OnInsert, OnRemove, Or, Query, Res, ResMut, Resource, Trigger,
+        With, Without,
+    },
+    query::QueryData,
+    schedule::{InternedScheduleLabel, ScheduleLabel, SystemSet},
+    system::SystemParam,
+};
+use bevy_math::{
+    IVec3, Vec3A,
+    bounding::{Aabb3d, BoundingVolume, IntersectsVolume},
+};
+use bevy_transform::{
+    TransformSystem,
+    components::{GlobalTransform, Transform},
+};
+use smallvec::SmallVec;
+
+#[cfg(feature = "render")]
+use bevy_ecs::{entity::EntityHashSet, prelude::Changed};
+
+#[cfg(feature = "render")]
+use crate::visibility::Visibility;
+use crate::{
+    bounds::{FlowAabb, VaneAabb, cell_count, transform_aabb},
+    flow::{Flow, FlowLayers, FlowSystems},
+    vane::{Vane, VaneSystems},
+    visibility::{VisibilityData, is_hidden},
+    volume::FlowVolume,
+};
+#[cfg(feature = "render")]
+use bevy_render::camera::{CameraProjection, Projection};
+
+/// Triggered on a flow or vane when it resumes taking part in sampling, after being hidden or
+/// disabled, or on entering an [`ActiveRegion`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Activate;
+
+/// Triggered on a flow or vane when it stops taking part in sampling because it was set to
+/// `Visibility::Hidden` or [`Disabled`], or left every [`ActiveRegion`].
+///
+/// Only the entity's own `Visibility` is considered, not that of its ancestors.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Deactivate;
+
+/// Makes a flow or vane only take part in sampling while its bounds overlap an [`ActiveRegion`],
+/// marked by [`Active`].
+///
+/// Entities without this component are unaffected by active regions. Hidden and disabled ones
+/// count as outside every region.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SpatialActivity;
+
+/// Marks a [`SpatialActivity`] entity whose bounds overlap an [`ActiveRegion`], within the budget
+/// set in [`ActivitySettings`], or forced by [`ForceActive`]. Managed by the [`ActivityPlugin`].
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Active;
+
+/// Keeps a [`SpatialActivity`] entity [`Active`] wherever it is, outside the activation budget,
+/// such as a cutscene prop that must keep sampling off-screen. Hidden entities still stop.
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[require(SpatialActivity)]
+pub struct ForceActive;
+
+/// Keeps a [`SpatialActivity`] entity inactive even inside active regions, such as to freeze an
+/// expensive sensor for a while. Wins over [`ForceActive`].
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[require(SpatialActivity)]
+pub struct ForceInactive;
+
+/// How much a [`SpatialActivity`] entity matters when more are inside active regions than
+/// [`ActivitySettings`] allows to be [`Active`]. Higher priorities are activated first, and
+/// entities without one have priority `0`.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActivationPriority(pub i32);
+
+/// A box over its entity's unit cube that wakes the [`SpatialActivity`] flows and vanes it
+/// overlaps, such as the area around the player.
+///
+/// Entities already active stay so until they are `margin` meters clear of the box, so ones
+/// skimming its edge don't flicker in and out.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(Transform)]
+pub struct ActiveRegion {
+    pub margin: f32,
+    /// Lowers the [`ActivityTier`] of entities far from the region's center. Without tiers, every
+    /// entity inside is fully active.
+    pub tiers: Option<ActivityTiers>,
+    /// Only flows and vanes sharing one of these layers are woken, so an underwater region can
+    /// leave the air above it alone. Defaults to all layers.
+    pub layers: FlowLayers,
+}
+
+impl ActiveRegion {
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn with_tiers(mut self, tiers: ActivityTiers) -> Self {
+        self.tiers = Some(tiers);
+        self
+    }
+}
+
+impl Default for ActiveRegion {
+    fn default() -> Self {
+        Self {
+            margin: 1.0,
+            tiers: None,
+            layers: FlowLayers::all(),
+        }
+    }
+}
+
+/// How fully an [`Active`] entity takes part in sampling, set by the [`ActivityTiers`] of the
+/// regions it is in. Entities in several regions get the highest tier any of them gives.
+///
+/// Vanes in lower tiers sample less often, as set in
+/// [`VaneSettings`](crate::vane::VaneSettings).
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ActivityTier {
+    #[default]
+    Active,
+    Reduced,
+    Dormant,
+}
+
+/// Distances from an [`ActiveRegion`]'s center, in meters, past which entities drop to a lower
+/// [`ActivityTier`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActivityTiers {
+    pub reduced: f32,
+    pub dormant: f32,
+}
+
+impl ActivityTiers {
+    pub fn tier(&self, distance: f32) -> ActivityTier {
+        if distance > self.dormant {
+            ActivityTier::Dormant
+        } else if distance > self.reduced {
+            ActivityTier::Reduced
+        } else {
+            ActivityTier::Active
+        }
+    }
+}
+
+/// Keeps this entity's [`ActiveRegion`] around `camera`, so only flows and vanes near the view
+/// take part in sampling.
+///
+/// The region covers `distance` meters in every direction from the camera, or with `frustum`,
+/// the bounds of the camera's view out to `distance`. Frustum bounds need the `render` feature
+/// and a `Projection` on the camera, and fall back to the cube otherwise.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(ActiveRegion)]
+pub struct CameraActiveRegion {
+    pub camera: Entity,
+    pub distance: f32,
+    /// Copied to the [`ActiveRegion::margin`].
+    pub margin: f32,
+    pub frustum: bool,
+}
+
+impl CameraActiveRegion {
+    pub fn new(camera: Entity, distance: f32) -> Self {
+        Self {
+            camera,
+            distance,
+            margin: ActiveRegion::default().margin,
+            frustum: false,
+        }
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_frustum(mut self) -> Self {
+        self.frustum = true;
+        self
+    }
+}
+
+#[cfg(feature = "render")]
+type ProjectionData = Option<&'static Projection>;
+#[cfg(not(feature = "render"))]
+type ProjectionData = ();
+
+/// Query data for whether an entity is kept out of sampling by [`SpatialActivity`], read with
+/// [`is_inactive`].
+pub(crate) type ActivityData = (Has<SpatialActivity>, Has<Active>);
+
+pub(crate) fn is_inactive((spatial, active): (bool, bool)) -> bool {
+    spatial && !active
+}
+
+/// Sent when a [`SpatialActivity`] entity's bounds start overlapping an [`ActiveRegion`].
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionEntered {
+    pub entity: Entity,
+    pub region: Entity,
+}
+
+/// Sent when a [`SpatialActivity`] entity's bounds move clear of an [`ActiveRegion`]'s margin, or
+/// the region is despawned.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionExited {
+    pub entity: Entity,
+    pub region: Entity,
+}
+
+/// The [`SpatialActivity`] entities inside at least one [`ActiveRegion`], with the regions each
+/// is in, as of their latest check.
+///
+/// Entities left inactive by the activation budget in [`ActivitySettings`] or [`ForceInactive`]
+/// are still listed, and [`ForceActive`] ones only while actually inside.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ActiveEntities {
+    regions: EntityHashMap<SmallVec<[Entity; 2]>>,
+}
+
+impl ActiveEntities {
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.regions.contains_key(&entity)
+    }
+
+    /// The active regions `entity` is in, empty if it is in none.
+    pub fn regions(&self, entity: Entity) -> &[Entity] {
+        self.regions.get(&entity).map_or(&[], |regions| regions)
+    }
+
+    /// The entities inside `region`. Visits every active entity.
+    pub fn in_region(&self, region: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.regions
+            .iter()
+            .filter(move |(_, regions)| regions.contains(&region))
+            .map(|(&entity, _)| entity)
+    }
+
+    /// Iterates over the active entities and the regions each is in.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &[Entity])> {
+        self.regions
+            .iter()
+            .map(|(&entity, regions)| (entity, regions.as_slice()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Takes `entity` out of every region it is in.
+    fn leave(&mut self, entity: Entity, exited: &mut EventWriter<RegionExited>) {
+        for region in self.regions.remove(&entity).into_iter().flatten() {
+            exited.write(RegionExited { entity, region });
+        }
+    }
+}
+
+/// Every flow and vane that started or stopped taking part in sampling since the last batch, sent
+/// once per run of [`VaneSystems::Activity`] when [`ActivitySettings::batch`] is enabled.
+///
+/// Cheaper to handle than an [`Activate`] or [`Deactivate`] per entity when many change at once.
+#[derive(Event, Clone, Debug, Default)]
+pub struct ActivityBatch {
+    pub activated: Vec<Entity>,
+    pub deactivated: Vec<Entity>,
+}
+
+/// How the [`ActivityPlugin`] reports changes.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActivitySettings {
+    /// Whether to trigger [`Activate`] and [`Deactivate`] on each changed entity.
+    pub entity_events: bool,
+    /// Whether to send [`ActivityBatch`]es.
+    pub batch: bool,
+    /// The most [`SpatialActivity`] flows that may be [`Active`] at once, keeping those of highest
+    /// [`ActivationPriority`]. Unlimited when `None`.
+    pub max_active_flows: Option<usize>,
+    /// Like [`max_active_flows`](Self::max_active_flows), for vanes.
+    pub max_active_vanes: Option<usize>,
+    /// Runs of [`VaneSystems::Activity`] between checks of each [`SpatialActivity`] entity
+    /// against the [`ActiveRegion`]s. Entities are staggered so each run checks an even share.
+    pub interval: u32,
+}
+
+impl Default for ActivitySettings {
+    fn default() -> Self {
+        Self {
+            entity_events: true,
+            batch: false,
+            max_active_flows: None,
+            max_active_vanes: None,
+            interval: 1,
+        }
+    }
+}
+
+/// Changes waiting for the next [`ActivityBatch`].
+#[derive(Resource, Default)]
+struct PendingActivity(ActivityBatch);
+
+/// Reports flows and vanes becoming active or inactive as [`ActivitySettings`] asks.
+#[derive(SystemParam)]
+struct ActivityReporter<'w, 's> {
+    settings: Res<'w, ActivitySettings>,
+    pending: ResMut<'w, PendingActivity>,
+    commands: Commands<'w, 's>,
+}
+
+impl ActivityReporter<'_, '_> {
+    fn report(&mut self, entity: Entity, active: bool) {
+        if self.settings.entity_events {
+            if active {
+                self.commands.trigger_targets(Activate, entity);
+            } else {
+                self.commands.trigger_targets(Deactivate, entity);
+            }
+        }
+        if self.settings.batch {
+            let batch = &mut self.pending.0;
+            if active {
+                batch.activated.push(entity);
+            } else {
+                batch.deactivated.push(entity);
+            }
+        }
+    }
+}
+
+/// The stages of [`VaneSystems::Activity`], in order, for running gameplay between them.
+///
+/// Systems after [`Update`](Self::Update) see this run's [`Active`] markers, [`ActivityTier`]s,
+/// and [`ActiveEntities`].
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ActivitySystems {
+    /// Notices visibility changes and moves [`CameraActiveRegion`]s.
+    Track,
+    /// Checks [`SpatialActivity`] entities against the [`ActiveRegion`]s and sends
+    /// [`RegionEntered`] and [`RegionExited`].
+    Update,
+    /// Sends the [`ActivityBatch`].
+    Report,
+}
+
+/// Reports flows and vanes starting and stopping taking part in sampling.
+///
+/// [`VaneSystems::Activity`] runs in [`PostUpdate`] by default, after transform propagation and
+/// before [`FlowSystems`], so flows' and vanes' bounds are those from the previous frame. If the
+/// [`FlowPlugin`](crate::flow::FlowPlugin) runs in [`FixedUpdate`], use [`ActivityPlugin::fixed`]
+/// to match, or [`ActivityPlugin::new`] for any other schedule.
+pub struct ActivityPlugin {
+    pub schedule: InternedScheduleLabel,
+}
+
+impl ActivityPlugin {
+    pub fn new(schedule: impl ScheduleLabel) -> Self {
+        Self {
+            schedule: schedule.intern(),
+        }
+    }
+
+    pub fn fixed() -> Self {
+        Self::new(FixedUpdate)
+    }
+}
+
+impl Default for ActivityPlugin {
+    fn default() -> Self {
+        Self {
+            schedule: PostUpdate.intern(),
+        }
+    }
+}
+
+impl Plugin for ActivityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivitySettings>()
+            .init_resource::<PendingActivity>()
+            .init_resource::<ActiveEntities>()
+            .add_event::<ActivityBatch>()
+            .add_event::<RegionEntered>()
+            .add_event::<RegionExited>()
+            .add_observer(deactivate_disabled)
+            .add_observer(activate_enabled)
+            .configure_sets(
+                self.schedule,
+                VaneSystems::Activity
+                    .after(TransformSystem::TransformPropagate)
+                    .before(FlowSystems),
+            )
+            .configure_sets(
+                self.schedule,
+                (
+                    ActivitySystems::Track,
+                    ActivitySystems::Update,
+                    ActivitySystems::Report,
+                )
+                    .chain()
+                    .in_set(VaneSystems::Activity),
+            )
+            .add_systems(
+                self.schedule,
+                (
+                    (
+                        #[cfg(feature = "render")]
+                        track_visibility,
+                        follow_cameras,
+                    )
+                        .in_set(ActivitySystems::Track),
+                    update_activities.in_set(ActivitySystems::Update),
+                    send_activity_batch.in_set(ActivitySystems::Report),
+                ),
+            );
+    }
+}
+
+/// Deactivates disabled flows and vanes, taking [`SpatialActivity`] ones out of their regions so
+/// pooled objects don't hold on to activation slots.
+fn deactivate_disabled(
+    trigger: Trigger<OnInsert, Disabled>,
+    tracked: Query<
+        (Has<SpatialActivity>, Has<Active>),
+        (Or<(With<Flow>, With<Vane>)>, With<Disabled>),
+    >,
+    mut active_entities: ResMut<ActiveEntities>,
+    mut exited: EventWriter<RegionExited>,
+    mut reporter: ActivityReporter,
+) {
+    let entity = trigger.target();
+    let Ok((spatial, active)) = tracked.get(entity) else {
+        return;
+    };
+    if spatial {
+        active_entities.leave(entity, &mut exited);
+        if !active {
+            return;
+        }
+        reporter.commands.entity(entity).remove::<Active>();
+    }
+    reporter.report(entity, false);
+}
+
+/// Reactivates enabled flows and vanes. [`SpatialActivity`] ones wait for their next check.
+fn activate_enabled(
+    trigger: Trigger<OnRemove, Disabled>,
+    tracked: Query<Has<SpatialActivity>, (Or<(With<Flow>, With<Vane>)>, With<Disabled>)>,
+    mut reporter: ActivityReporter,
+) {
+    if tracked.get(trigger.target()) == Ok(false) {
+        reporter.report(trigger.target(), true);
+    }
+}
+
+#[cfg(feature = "render")]
+fn track_visibility(
+    mut hidden: Local<EntityHashSet>,
+    // Spatial entities are reported by `update_activities`, which treats hidden ones as outside.
+    changed: Query<
+        (Entity, &Visibility),
+        (
+            Changed<Visibility>,
+            Or<(With<Flow>, With<Vane>)>,
+            Without<SpatialActivity>,
+        ),
+    >,
+    mut reporter: ActivityReporter,
+) {
+    for (entity, visibility) in &changed {
+        if *visibility == Visibility::Hidden {
+            if hidden.insert(entity) {
+                reporter.report(entity, false);
+            }
+        } else if hidden.remove(&entity) {
+            reporter.report(entity, true);
+        }
+    }
+}
+
+/// Moves camera regions into place in world space, whatever their parents.
+fn follow_cameras(
+    cameras: Query<(&GlobalTransform, ProjectionData), Without<CameraActiveRegion>>,
+    parents: Query<&GlobalTransform, Without<CameraActiveRegion>>,
+    mut regions: Query<(
+        &CameraActiveRegion,
+        &mut ActiveRegion,
+        &mut Transform,
+        &mut GlobalTransform,
+        Option<&ChildOf>,
+    )>,
+) {
+    for (follow, mut region, mut transform, mut global_transform, parent) in &mut regions {
+        let Ok((camera, projection)) = cameras.get(follow.camera) else {
+            continue;
+        };
+        let center = camera.translation_vec3a();
+        let reach = Vec3A::splat(follow.distance);
+        #[cfg(feature = "render")]
+        let frustum = projection.filter(|_| follow.frustum).map(|projection| {
+            // Views look down -Z, so the far corners sit at negative depth.
+            let corners = projection.get_frustum_corners(0.0, -follow.distance);
+            corners.iter().fold(
+                (Vec3A::splat(f32::MAX), Vec3A::splat(f32::MIN)),
+                |(min, max), &corner| {
+                    let corner = camera.affine().transform_point3a(corner);
+                    (min.min(corner), max.max(corner))
+                },
+            )
+        });
+        #[cfg(not(feature = "render"))]
+        let frustum = {
+            let () = projection;
+            None
+        };
+        let (min, max) = frustum.unwrap_or((center - reach, center + reach));
+        let bounds = GlobalTransform::from(Transform {
+            translation: ((min + max) * 0.5).into(),
+            rotation: Default::default(),
+            scale: (max - min).into(),
+        });
+        let local = match parent.map(|parent| parents.get(parent.parent())) {
+            Some(Ok(parent)) => bounds.reparented_to(parent),
+            _ => bounds.compute_transform(),
+        };
+        transform.set_if_neq(local);
+        // Propagation has already run this frame.
+        global_transform.set_if_neq(bounds);
+        if region.margin != follow.margin {
+            region.margin = follow.margin;
+        }
+    }
+}
+
+/// Activates [`SpatialActivity`] entities overlapping an [`ActiveRegion`] and deactivates those
+/// clear of every region's margin, keeping [`ActiveEntities`] in step.
+///
+/// Runs before [`FlowSystems`], so it sees the bounds from the previous frame.
+fn update_activities(
+    mut run: Local<u32>,
+    regions: Query<(Entity, &ActiveRegion, &GlobalTransform)>,
+    tracked: Query<TrackedEntity, With<SpatialActivity>>,
+    mut active_entities: ResMut<ActiveEntities>,
+    mut entered: EventWriter<RegionEntered>,
+    mut exited: EventWriter<RegionExited>,
+    mut reporter: ActivityReporter,
+) {
+    let grid = RegionGrid::new(
+        regions
+            .iter()
+            .map(|(entity, region, transform)| ActiveBounds {
+                entity,
+                aabb: transform_aabb(&transform.affine(), &FlowVolume::Box.local_aabb()),
+                margin: region.margin,
+                tiers: region.tiers,
+                layers: region.layers,
+            })
+            .collect(),
+    );
+    // Despawned and untracked entities leave without events.
+    active_entities
+        .regions
+        .retain(|&entity, _| tracked.contains(entity));
+
+    let interval = reporter.settings.interval.max(1);
+    *run = run.wrapping_add(1);
+    for tracked in &tracked {
+        let entity = tracked.entity;
+        // Hidden entities leave right away rather than on their next staggered check.
+        if is_hidden(tracked.visibility) {
+            active_entities.leave(entity, &mut exited);
+            continue;
+        }
+        if entity.index().wrapping_add(*run) % interval != 0 {
+            continue;
+        }
+        let Some(aabb) = tracked
+            .flow_aabb
+            .map(|aabb| aabb.0)
+            .or(tracked.vane_aabb.map(|aabb| aabb.0))
+        else {
+            continue;
+        };
+        let previous = active_entities.regions.remove(&entity).unwrap_or_default();
+        let mut current = SmallVec::<[Entity; 2]>::new();
+        let mut new_tier = ActivityTier::Dormant;
+        let mut tiered = false;
+        for region in grid
+            .candidates(&aabb)
+            .filter(|region| region.layers.intersects(tracked.layers))
+        {
+            // Only regions the entity is already in get the margin.
+            let inside = previous.contains(&region.entity);
+            if !current.contains(&region.entity) && region.overlaps(&aabb, inside) {
+                current.push(region.entity);
+                new_tier = new_tier.min(region.tier(&aabb));
+                tiered |= region.tiers.is_some();
+            }
+        }
+        // Entities only in untiered regions carry no tier.
+        let new_tier = tiered.then_some(new_tier);
+        if new_tier != tracked.tier.copied() {
+            let mut commands = reporter.commands.entity(entity);
+            match new_tier {
+                Some(tier) => commands.insert(tier),
+                None => commands.remove::<ActivityTier>(),
+            };
+        }
+        for &region in current.iter().filter(|region| !previous.contains(region)) {
+            entered.write(RegionEntered { entity, region });
+        }
+        for &region in previous.iter().filter(|region| !current.contains(region)) {
+            exited.write(RegionExited { entity, region });
+        }
+
+        if !current.is_empty() {
+            active_entities.regions.insert(entity, current);
+        }
+    }
+
+    // Entities inside a region are activated by priority until the budget runs out, keeping
+    // those already active on ties so equal entities don't trade places.
+    let mut inside: Vec<_> = tracked
+        .iter()
+        .filter(|tracked| {
+            active_entities.contains(tracked.entity)
+                && !tracked.force_active
+                && !tracked.force_inactive
+        })
+        .map(|tracked| {
+            let priority = tracked.priority.copied().unwrap_or_default();
+            (
+                Reverse(priority),
+                !tracked.active,
+                tracked.entity,
+                tracked.flow_aabb.is_some(),
+            )
+        })
+        .collect();
+    inside.sort_unstable();
+    let settings = *reporter.settings;
+    let (mut flows, mut vanes) = (0, 0);
+    for (_, inactive, entity, is_flow) in inside {
+        let (count, max) = if is_flow {
+            (&mut flows, settings.max_active_flows)
+        } else {
+            (&mut vanes, settings.max_active_vanes)
+        };
+        let allowed = max.is_none_or(|max| *count < max);
+        if allowed {
+            *count += 1;
+        }
+        set_active(&mut reporter, entity, !inactive, allowed);
+    }
+    for tracked in &tracked {
+        let forced = tracked.force_active || tracked.force_inactive;
+        if forced || !active_entities.contains(tracked.entity) {
+            let active =
+                tracked.force_active && !tracked.force_inactive && !is_hidden(tracked.visibility);
+            set_active(&mut reporter, tracked.entity, tracked.active, active);
+        }
+    }
+}
+
+/// The components of a [`SpatialActivity`] entity that [`update_activities`] reads.
+#[derive(QueryData)]
+struct TrackedEntity {
+    entity: Entity,
+    layers: &'static FlowLayers,
+    visibility: VisibilityData,
+    flow_aabb: Option<&'static FlowAabb>,
+    vane_aabb: Option<&'static VaneAabb>,
+    priority: Option<&'static ActivationPriority>,
+    tier: Option<&'static ActivityTier>,
+    active: Has<Active>,
+    force_active: Has<ForceActive>,
+    force_inactive: Has<ForceInactive>,
+}
+
+fn set_active(reporter: &mut ActivityReporter, entity: Entity, was: bool, active: bool) {
+    if was == active {
+        return;
+    }
+    if active {
+        reporter.commands.entity(entity).insert(Active);
+    } else {
+        reporter.commands.entity(entity).remove::<Active>();
+    }
+    reporter.report(entity, active);
+}
+
+/// An [`ActiveRegion`]'s world-space bounds for one run of [`update_activities`].
+struct ActiveBounds {
+    entity: Entity,
+    aabb: Aabb3d,
+    margin: f32,
+    tiers: Option<ActivityTiers>,
+    layers: FlowLayers,
+}
+
+impl ActiveBounds {
+    /// Whether `aabb` overlaps the region, grown by its margin for entities already `inside`.
+    fn overlaps(&self, aabb: &Aabb3d, inside: bool) -> bool {
+        let margin = Vec3A::splat(if inside { self.margin } else { 0.0 });
+        let region = Aabb3d {
+            min: self.aabb.min - margin,
+            max: self.aabb.max + margin,
+        };
+        region.intersects(aabb)
+    }
+
+    /// The tier an entity with bounds `aabb` gets from this region.
+    fn tier(&self, aabb: &Aabb3d) -> ActivityTier {
+        self.tiers.map_or(ActivityTier::Active, |tiers| {
+            tiers.tier(self.aabb.center().distance(aabb.center()))
+        })
+    }
+}
+
+/// A uniform grid over [`ActiveBounds`], so each entity only tests the regions near it.
+///
+/// Cells are as large as the largest region, so each region lands in at most eight of them.
+struct RegionGrid {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<usize>>,
+    regions: Vec<ActiveBounds>,
+}
+
+impl RegionGrid {
+    /// Entities covering more cells than this test every region instead.
+    const MAX_CELLS: u32 = 64;
+
+    fn new(regions: Vec<ActiveBounds>) -> Self {
+        let cell_size = regions
+            .iter()
+            .map(|region| (region.aabb.max - region.aabb.min).max_element() + 2.0 * region.margin)
+            .fold(1.0, f32::max);
+        let mut grid = Self {
+            cell_size,
+            cells: HashMap::new(),
+            regions: Vec::new(),
+        };
+        for (index, region) in regions.iter().enumerate() {
+            let margin = Vec3A::splat(region.margin);
+            let (min, max) = (
+                grid.cell(region.aabb.min - margin),
+                grid.cell(region.aabb.max + margin),
+            );
+            for z in min.z..=max.z {
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        grid.cells
+                            .entry(IVec3::new(x, y, z))
+                            .or_default()
+                            .push(index);
+                    }
+                }
+            }
+        }
+        grid.regions = regions;
+        grid
+    }
+
+    fn cell(&self, position: Vec3A) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    /// The regions that may overlap `aabb`, possibly more than once.
+    fn candidates(&self, aabb: &Aabb3d) -> impl Iterator<Item = &ActiveBounds> {
+        let (min, max) = (self.cell(aabb.min), self.cell(aabb.max));
+        let wide = cell_count(min, max) > u64::from(Self::MAX_CELLS);
+        let near = (!wide).then(|| {
+            (min.z..=max.z)
+                .flat_map(move |z| (min.y..=max.y).map(move |y| (y, z)))
+                .flat_map(move |(y, z)| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flatten()
+                .map(|&index| &self.regions[index])
+        });
+        let all = self.regions.iter().filter(move |_| wide);
+        all.chain(near.into_iter().flatten())
+    }
+}
+
+fn send_activity_batch(
+    mut pending: ResMut<PendingActivity>,
+    mut batches: EventWriter<ActivityBatch>,
+) {
+    let batch = core::mem::take(&mut pending.0);
+    if !batch.activated.is_empty() || !batch.deactivated.is_empty() {
+        batches.write(batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use super::*;
+    use crate::test_utils::FlowWorldBuilder;
+
+    #[test]
+    fn camera_regions_follow_in_world_space() {
+        let mut world = FlowWorldBuilder::default();
+        let camera = world
+            .world_mut()
+            .spawn(Transform::from_xyz(5.0, 0.0, 0.0))
+            .id();
+        let parent = world
+            .world_mut()
+            .spawn(Transform::from_xyz(100.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)))
+            .id();
+        let region = world
+            .world_mut()
+            .spawn((CameraActiveRegion::new(camera, 2.0), ChildOf(parent)))
+            .id();
+
+        for _ in 0..2 {
+            world.step(1);
+            let bounds = world.world().get::<GlobalTransform>(region).unwrap();
+            assert!(
+                bounds.translation().abs_diff_eq(Vec3::X * 5.0, 1e-4),
+                "the region is centered on {}",
+                bounds.translation()
+            );
+            assert!(bounds.scale().abs_diff_eq(Vec3::splat(4.0), 1e-4));
+        }
+    }
+
+    #[test]
+    fn huge_entities_test_every_region() {
+        let grid = RegionGrid::new(vec![ActiveBounds {
+            entity: Entity::PLACEHOLDER,
+            aabb: Aabb3d::new(Vec3::ZERO, Vec3::ONE),
+            margin: 0.0,
+            tiers: None,
+            layers: FlowLayers::all(),
+        }]);
+        let everywhere = Aabb3d {
+            min: Vec3A::splat(-f32::MAX),
+            max: Vec3A::splat(f32::MAX),
+        };
+        assert_eq!(grid.candidates(&everywhere).count(), 1);
+    }
+
+    fn spatial_vane(world: &mut FlowWorldBuilder, x: f32) -> Entity {
+        let vane = world.vane(Vec3::X * x);
+        world.world_mut().entity_mut(vane).insert(SpatialActivity);
+        vane
+    }
+
+    fn is_active(world: &FlowWorldBuilder, entity: Entity) -> bool {
+        world.world().entity(entity).contains::<Active>()
+    }
+
+    fn move_to(world: &mut FlowWorldBuilder, entity: Entity, x: f32) {
+        let mut transform = world.world_mut().get_mut::<Transform>(entity).unwrap();
+        transform.translation.x = x;
+        // Activity sees the bounds from the previous frame.
+        world.step(2);
+    }
+
+    #[test]
+    fn budgets_activate_by_priority() {
+        let mut world = FlowWorldBuilder::default();
+        world.world_mut().insert_resource(ActivitySettings {
+            max_active_vanes: Some(1),
+            ..Default::default()
+        });
+        world.world_mut().spawn((
+            ActiveRegion::default(),
+            Transform::from_scale(Vec3::splat(20.0)),
+        ));
+        let low = spatial_vane(&mut world, 1.0);
+        let high = spatial_vane(&mut world, 2.0);
+        world
+            .world_mut()
+            .entity_mut(high)
+            .insert(ActivationPriority(1));
+        world.step(3);
+        assert!(is_active(&world, high) && !is_active(&world, low));
+
+        // Over budget, the lower priority stays inactive, and keeps its place in the region.
+        world.step(5);
+        assert!(is_active(&world, high) && !is_active(&world, low));
+        assert!(world.world().resource::<ActiveEntities>().contains(low));
+
+        // Equal priorities keep the entity already active.
+        world
+            .world_mut()
+            .entity_mut(low)
+            .insert(ActivationPriority(1));
+        world.step(3);
+        assert!(is_active(&world, high) && !is_active(&world, low));
+
+        world
+            .world_mut()
+            .entity_mut(low)
+            .insert(ActivationPriority(2));
+        world.step(1);
+        assert!(is_active(&world, low) && !is_active(&world, high));
+    }
+
+    #[test]
+    fn tiers_drop_with_distance() {
+        let mut world = FlowWorldBuilder::default();
+        let tiers = ActivityTiers {
+            reduced: 2.0,
+            dormant: 5.0,
+        };
+        world.world_mut().spawn((
+            ActiveRegion::default().with_tiers(tiers),
+            Transform::from_scale(Vec3::splat(20.0)),
+        ));
+        let vanes = [1.0, 3.0, 8.0].map(|x| spatial_vane(&mut world, x));
+        let outside = spatial_vane(&mut world, 15.0);
+        world.step(3);
+
+        let tier_of = |vane| world.world().get::<ActivityTier>(vane).copied();
+        assert_eq!(
+            vanes.map(tier_of),
+            [
+                Some(ActivityTier::Active),
+                Some(ActivityTier::Reduced),
+                Some(ActivityTier::Dormant)
+            ]
+        );
+        assert_eq!(tier_of(outside), None);
+        assert!(vanes.iter().all(|&vane| is_active(&world, vane)));
+        assert!(!is_active(&world, outside));
+    }
+
+    #[test]
+    fn margins_keep_edge_entities_from_flapping() {
+        let mut world = FlowWorldBuilder::default();
+        world.world_mut().spawn((
+            ActiveRegion {
+                margin: 1.0,
+                ..Default::default()
+            },
+            Transform::from_scale(Vec3::splat(4.0)),
+        ));
+        let vane = spatial_vane(&mut world, 1.0);
+        world.step(3);
+        assert!(is_active(&world, vane));
+
+        // Past the edge but inside the margin, it stays active however long it lingers.
+        for x in [2.5, 2.9, 2.2, 2.9] {
+            move_to(&mut world, vane, x);
+            assert!(is_active(&world, vane), "deactivated at {x}");
+        }
+        move_to(&mut world, vane, 3.5);
+        assert!(!is_active(&world, vane));
+
+        // Coming back, the margin doesn't apply until it is inside again.
+        for x in [2.9, 2.5, 2.2] {
+            move_to(&mut world, vane, x);
+            assert!(!is_active(&world, vane), "activated at {x}");
+        }
+        move_to(&mut world, vane, 1.5);
+        assert!(is_active(&world, vane));
+    }
+}
@@ -0,0 +1,275 @@
+use std::sync::Arc;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    hierarchy::ChildOf,
+    prelude::{Component, IntoScheduleConfigs, Query, Res},
+};
+use bevy_math::{Dir3, Vec3, curve::Curve};
+use bevy_transform::components::GlobalTransform;
+
+use crate::{
+    drive::BodyVelocity,
+    units::VaneUnits,
+    vane::{Vane, VaneSamples, VaneSystems},
+};
+
+/// A dimensionless aerodynamic coefficient as a function of an angle in radians.
+pub type CoefficientCurve = Arc<dyn Curve<f32> + Send + Sync>;
+
+/// A sail that turns the apparent wind into thrust and heeling torque.
+///
+/// The sail is treated as a flat plate: its force acts along the sail normal with magnitude
+/// `0.5 * ρ * |w|² * area * efficiency(α)`, where `w` is the apparent wind (the sampled flow
+/// relative to the [`BodyVelocity`], if any) and `α` is the angle between `w` and the sail plane.
+///
+/// Thrust and heel are measured against the hull, which is the sail's parent if it has one.
+#[derive(Component, Clone)]
+#[require(Vane, SailForce)]
+pub struct Sail {
+    /// Sail area in square meters.
+    pub area: f32,
+    /// Normal of the sail plane in the sail's local space.
+    pub normal_axis: Dir3,
+    /// Force coefficient sampled by the angle of attack, in radians from `0` to `π/2`.
+    pub efficiency_curve: CoefficientCurve,
+}
+
+/// The output of a [`Sail`], updated every frame.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct SailForce {
+    /// Total world-space force on the sail in newtons.
+    pub force: Vec3,
+    /// Component of `force` along the hull's forward axis.
+    pub thrust: f32,
+    /// Torque of `force` about the hull's forward axis in newton-meters, applied at the sail's
+    /// position relative to the hull.
+    pub heeling_torque: f32,
+}
+
+/// A lifting surface such as a wing, glider, or arrow fletching.
+///
+/// The surface's chord runs along its local forward axis (`-Z`), with `+Y` as its upper side and
+/// `X` as its span. The angle of attack is measured from the chord to the relative airflow in the
+/// chord-normal plane, in radians from `-π` to `π`, positive when the flow comes from below.
+///
+/// Lift acts perpendicular to both the airflow and the span, while drag acts along the airflow.
+/// Each has magnitude `0.5 * ρ * |v|² * area * c(α)` for its coefficient curve `c`.
+#[derive(Component, Clone)]
+#[require(Vane, AeroForce)]
+pub struct AeroSurface {
+    /// Reference area in square meters.
+    pub area: f32,
+    /// Lift coefficient sampled by the angle of attack.
+    pub lift_curve: CoefficientCurve,
+    /// Drag coefficient sampled by the angle of attack.
+    pub drag_curve: CoefficientCurve,
+}
+
+/// The output of an [`AeroSurface`], updated every frame.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AeroForce {
+    /// World-space lift in newtons.
+    pub lift: Vec3,
+    /// World-space drag in newtons.
+    pub drag: Vec3,
+    /// Angle of attack in radians.
+    pub angle_of_attack: f32,
+}
+
+impl AeroForce {
+    pub fn total(&self) -> Vec3 {
+        self.lift + self.drag
+    }
+}
+
+pub struct AeroPlugin;
+
+impl Plugin for AeroPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VaneUnits>().add_systems(
+            Update,
+            (update_sails, update_aero_surfaces).in_set(VaneSystems::Respond),
+        );
+    }
+}
+
+fn update_sails(
+    mut sails: Query<(
+        &Sail,
+        &VaneSamples,
+        &GlobalTransform,
+        Option<&BodyVelocity>,
+        Option<&ChildOf>,
+        &mut SailForce,
+    )>,
+    hulls: Query<&GlobalTransform>,
+    units: Res<VaneUnits>,
+) {
+    for (sail, samples, transform, body_velocity, child_of, mut output) in &mut sails {
+        let flow = samples.mean();
+        let apparent = flow.velocity() - body_velocity.map_or(Vec3::ZERO, |v| v.0);
+        let normal = transform.rotation() * sail.normal_axis.as_vec3();
+
+        let along_normal = apparent.dot(normal);
+        let angle = (along_normal.abs() / apparent.length().max(f32::EPSILON))
+            .min(1.0)
+            .asin();
+        let coefficient = sail.efficiency_curve.sample_clamped(angle);
+        let force = normal
+            * along_normal.signum()
+            * 0.5
+            * units.density(flow.density)
+            * apparent.length_squared()
+            * sail.area
+            * coefficient;
+
+        let hull = child_of
+            .and_then(|child_of| hulls.get(child_of.parent()).ok())
+            .unwrap_or(transform);
+        let forward = hull.forward().as_vec3();
+        let lever = transform.translation() - hull.translation();
+
+        *output = SailForce {
+            force,
+            thrust: force.dot(forward),
+            heeling_torque: lever.cross(force).dot(forward),
+        };
+    }
+}
+
+fn update_aero_surfaces(
+    mut surfaces: Query<(
+        &AeroSurface,
+        &VaneSamples,
+        &GlobalTransform,
+        Option<&BodyVelocity>,
+        &mut AeroForce,
+    )>,
+    units: Res<VaneUnits>,
+) {
+    for (surface, samples, transform, body_velocity, mut output) in &mut surfaces {
+        let flow = samples.mean();
+        let airflow = flow.velocity() - body_velocity.map_or(Vec3::ZERO, |v| v.0);
+        let Ok(direction) = Dir3::new(airflow) else {
+            *output = AeroForce::default();
+            continue;
+        };
+
+        let local = transform.rotation().inverse() * direction;
+        let angle_of_attack = local.y.atan2(local.z);
+        let span = transform.right().as_vec3();
+        let lift_direction = direction.cross(span).normalize_or_zero();
+
+        let pressure = 0.5 * units.density(flow.density) * airflow.length_squared() * surface.area;
+        *output = AeroForce {
+            lift: lift_direction * pressure * surface.lift_curve.sample_clamped(angle_of_attack),
+            drag: direction * pressure * surface.drag_curve.sample_clamped(angle_of_attack),
+            angle_of_attack,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::FRAC_PI_2;
+
+    use bevy_math::{
+        Quat,
+        curve::{FunctionCurve, Interval},
+    };
+    use bevy_transform::components::Transform;
+
+    use super::*;
+    use crate::{flow::AIR_DENSITY, test_utils::FlowWorldBuilder};
+
+    fn constant(coefficient: f32) -> CoefficientCurve {
+        Arc::new(FunctionCurve::new(Interval::EVERYWHERE, move |_| {
+            coefficient
+        }))
+    }
+
+    /// `0.5 * ρ * |v|²` for air at `speed`.
+    fn dynamic_pressure(speed: f32) -> f32 {
+        0.5 * AIR_DENSITY * speed * speed
+    }
+
+    #[test]
+    fn sails_heel_in_a_crosswind() {
+        let mut world = FlowWorldBuilder::default();
+        world.uniform_flow(Vec3::Z * 5.0, Transform::from_scale(Vec3::splat(100.0)));
+        // The hull faces +X, and the sail above it faces the wind along +Z.
+        let hull = world
+            .world_mut()
+            .spawn(Transform::from_rotation(Quat::from_rotation_y(-FRAC_PI_2)))
+            .id();
+        let sail = world
+            .world_mut()
+            .spawn((
+                Sail {
+                    area: 2.0,
+                    normal_axis: Dir3::X,
+                    efficiency_curve: constant(0.8),
+                },
+                Transform::from_xyz(0.0, 2.0, 0.0),
+                ChildOf(hull),
+            ))
+            .id();
+        world.step(3);
+
+        let output = *world.world().get::<SailForce>(sail).unwrap();
+        let force = dynamic_pressure(5.0) * 2.0 * 0.8;
+        assert!(
+            output.force.abs_diff_eq(Vec3::Z * force, 1e-3),
+            "{output:?}"
+        );
+        assert!(output.thrust.abs() < 1e-3, "{output:?}");
+        assert!(
+            (output.heeling_torque - 2.0 * force).abs() < 1e-2,
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn aero_surfaces_lift_and_drag() {
+        let mut world = FlowWorldBuilder::default();
+        // Head-on wind along the chord, from the nose at -Z.
+        world.uniform_flow(Vec3::Z * 10.0, Transform::from_scale(Vec3::splat(100.0)));
+        let surface = world
+            .world_mut()
+            .spawn(AeroSurface {
+                area: 0.5,
+                lift_curve: constant(1.2),
+                drag_curve: constant(0.1),
+            })
+            .id();
+        let moving = world
+            .world_mut()
+            .spawn((
+                AeroSurface {
+                    area: 0.5,
+                    lift_curve: constant(1.2),
+                    drag_curve: constant(0.1),
+                },
+                BodyVelocity(Vec3::Z * 10.0),
+            ))
+            .id();
+        world.step(3);
+
+        let output = *world.world().get::<AeroForce>(surface).unwrap();
+        let pressure = dynamic_pressure(10.0) * 0.5;
+        assert!(output.angle_of_attack.abs() < 1e-4, "{output:?}");
+        assert!(
+            output.lift.abs_diff_eq(Vec3::Y * pressure * 1.2, 1e-3),
+            "{output:?}"
+        );
+        assert!(
+            output.drag.abs_diff_eq(Vec3::Z * pressure * 0.1, 1e-3),
+            "{output:?}"
+        );
+
+        // Moving with the wind, the surface feels no airflow.
+        let output = *world.world().get::<AeroForce>(moving).unwrap();
+        assert_eq!(output, AeroForce::default());
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use bevy_ecs::prelude::Resource;
+use bevy_math::{Dir3, Vec3};
+
+use crate::flow::{FlowLayers, FlowVector};
+
+/// A flow that fills the whole world, for the common case of a prevailing wind everywhere.
+///
+/// When present, the ambient flow is the base every composition starts from, before any
+/// [`Flow`](crate::flow::Flow) is blended in, and needs no volume or region setup.
+#[derive(Resource, Clone)]
+pub struct AmbientFlow {
+    /// The layers the ambient flow contributes to.
+    pub layers: FlowLayers,
+    source: AmbientSource,
+}
+
+#[derive(Clone)]
+enum AmbientSource {
+    Uniform(FlowVector),
+    Analytic(Arc<dyn Fn(Vec3) -> FlowVector + Send + Sync>),
+}
+
+impl AmbientFlow {
+    /// The same flow at every point, on all layers.
+    pub fn uniform(flow: FlowVector) -> Self {
+        Self {
+            layers: FlowLayers::all(),
+            source: AmbientSource::Uniform(flow),
+        }
+    }
+
+    /// A flow computed from the world-space sample position, on all layers.
+    pub fn analytic(f: impl Fn(Vec3) -> FlowVector + Send + Sync + 'static) -> Self {
+        Self {
+            layers: FlowLayers::all(),
+            source: AmbientSource::Analytic(Arc::new(f)),
+        }
+    }
+
+    /// A uniform wind of Beaufort force `level` towards `direction`, on all layers. See
+    /// [`FlowVector::beaufort`].
+    pub fn beaufort(level: f32, direction: Dir3) -> Self {
+        Self::uniform(FlowVector::beaufort(level, direction))
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Samples the ambient flow at a world-space `position`.
+    pub fn sample(&self, position: Vec3) -> FlowVector {
+        match &self.source {
+            AmbientSource::Uniform(flow) => *flow,
+            AmbientSource::Analytic(f) => f(position),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{Asset, AssetApp, Assets, Handle};
+use bevy_ecs::prelude::{Commands, Component, Entity, IntoScheduleConfigs, Query, Res, ResMut};
+use bevy_math::UVec3;
+use bevy_reflect::TypePath;
+use bevy_time::Time;
+use bevy_transform::components::Transform;
+
+use crate::{
+    field::FlowField,
+    flow::{Flow, FlowSystems},
+};
+
+/// A sequence of equally sized [`FlowField`] frames, such as a baked simulation, played back by
+/// [`FlowPlayback`].
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct AnimatedFlowField {
+    frames: Vec<FlowField>,
+    frame_rate: f32,
+}
+
+impl AnimatedFlowField {
+    /// Creates an animation from its frames, played at `frame_rate` frames per second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no frames, if the frames differ in size, or if `frame_rate` isn't
+    /// positive.
+    pub fn new(frames: Vec<FlowField>, frame_rate: f32) -> Self {
+        let size = frames.first().expect("animation has no frames").size();
+        assert!(
+            frames.iter().all(|frame| frame.size() == size),
+            "animation frames differ in size"
+        );
+        assert!(frame_rate > 0.0, "frame rate must be positive");
+        Self { frames, frame_rate }
+    }
+
+    pub fn frames(&self) -> &[FlowField] {
+        &self.frames
+    }
+
+    pub fn frame_rate(&self) -> f32 {
+        self.frame_rate
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.frames[0].size()
+    }
+
+    /// Length of the animation in seconds. When looping, the last frame blends back into the
+    /// first.
+    pub fn duration(&self, looping: bool) -> f32 {
+        let intervals = if looping {
+            self.frames.len()
+        } else {
+            self.frames.len() - 1
+        };
+        intervals as f32 / self.frame_rate
+    }
+
+    /// Writes the animation at `time` seconds into `output`, interpolating between frames.
+    pub fn write_frame(&self, time: f32, looping: bool, output: &mut FlowField) {
+        let count = self.frames.len();
+        let position = time * self.frame_rate;
+        let (from, to, t) = if looping {
+            let position = position.rem_euclid(count as f32);
+            let from = position as usize % count;
+            (from, (from + 1) % count, position.fract())
+        } else {
+            let position = position.clamp(0.0, (count - 1) as f32);
+            let from = position as usize;
+            (from, (from + 1).min(count - 1), position.fract())
+        };
+
+        let (from, to) = (&self.frames[from], &self.frames[to]);
+        let count = from.size().element_product() as usize;
+        for (index, output) in output.data_mut().iter_mut().enumerate().take(count) {
+            *output = from.get_index(index).lerp(to.get_index(index), t);
+        }
+    }
+}
+
+/// Plays an [`AnimatedFlowField`] through the entity's [`Flow`].
+///
+/// Each frame, the animation is interpolated into a field owned by the playback, and the entity's
+/// [`Flow`] is pointed at it.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct FlowPlayback {
+    pub animation: Handle<AnimatedFlowField>,
+    /// Playback speed, where `1` is real time. Negative speeds play backwards.
+    pub speed: f32,
+    pub looping: bool,
+    time: f32,
+    output: Option<Handle<FlowField>>,
+}
+
+impl FlowPlayback {
+    pub fn new(animation: Handle<AnimatedFlowField>) -> Self {
+        Self {
+            animation,
+            speed: 1.0,
+            looping: true,
+            time: 0.0,
+            output: None,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// The playback position in seconds, wrapped to the animation when looping.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+}
+
+pub struct AnimatedFlowPlugin;
+
+impl Plugin for AnimatedFlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AnimatedFlowField>()
+            .add_systems(PostUpdate, play_animated_fields.before(FlowSystems));
+    }
+}
+
+fn play_animated_fields(
+    time: Res<Time>,
+    mut playbacks: Query<(Entity, &mut FlowPlayback, Option<&Flow>)>,
+    animations: Res<Assets<AnimatedFlowField>>,
+    mut fields: ResMut<Assets<FlowField>>,
+    mut commands: Commands,
+) {
+    for (entity, mut playback, flow) in &mut playbacks {
+        let Some(animation) = animations.get(&playback.animation) else {
+            continue;
+        };
+        playback.time += time.delta_secs() * playback.speed;
+        if !playback.looping {
+            playback.time = playback.time.clamp(0.0, animation.duration(false));
+        } else if animation.duration(true) > 0.0 {
+            // Wrapped so long-running loops don't lose precision.
+            playback.time = playback.time.rem_euclid(animation.duration(true));
+        }
+
+        let output = match &playback.output {
+            Some(output) if fields.get(output).map(FlowField::size) == Some(animation.size()) => {
+                output.clone()
+            }
+            _ => {
+                let output = fields.add(FlowField::new(animation.size()));
+                playback.output = Some(output.clone());
+                output
+            }
+        };
+        if let Some(field) = fields.get_mut(&output) {
+            animation.write_frame(playback.time, playback.looping, field);
+        }
+        if flow.is_none_or(|flow| flow.field != output) {
+            commands.entity(entity).insert(Flow::new(output));
+        }
+    }
+}
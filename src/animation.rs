@@ -0,0 +1,26 @@
+//! Animatable flow properties, for keyframing wind in animation clips.
+//!
+//! Each function returns a property to pair with a curve in an
+//! [`AnimatableCurve`](bevy_animation::animation_curves::AnimatableCurve):
+//!
+//! ```ignore
+//! let curve = AnimatableCurve::new(vane::animation::flow_influence(), gust_curve);
+//! clip.add_curve_to_target(target, curve);
+//! ```
+
+use bevy_animation::{
+    animated_field,
+    animation_curves::{AnimatableProperty, AnimatedField},
+};
+
+use crate::{envelope::FlowEnvelope, flow::FlowInfluence};
+
+/// The strength of a flow, through its [`FlowInfluence`].
+pub fn flow_influence() -> impl AnimatableProperty<Property = f32> {
+    animated_field!(FlowInfluence::0)
+}
+
+/// The [`gain`](FlowEnvelope::gain) of a flow's fade envelope.
+pub fn envelope_gain() -> impl AnimatableProperty<Property = f32> {
+    animated_field!(FlowEnvelope::gain)
+}
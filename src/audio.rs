@@ -0,0 +1,93 @@
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::{
+    Component, IntoScheduleConfigs, Query, ReflectComponent, Res, ResMut, Resource, With,
+};
+use bevy_math::Vec3;
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+use bevy_time::Time;
+use bevy_transform::components::GlobalTransform;
+
+use crate::vane::{Vane, VaneSamples, VaneSystems};
+
+/// Marks the [`Vane`] whose samples drive [`AudioWind`], usually one attached to the audio
+/// listener. If several vanes are marked, an arbitrary one is used.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct WindListener;
+
+/// How [`AudioWind`] turns raw samples into audio parameters.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct AudioWindSettings {
+    /// The wind speed, in m/s, heard at full [`loudness`](AudioWind::loudness).
+    pub full_speed: f32,
+    /// Time constant of the smoothing applied to every output, in seconds.
+    pub smoothing: f32,
+    /// Time constant of the slow average that [`gustiness`](AudioWind::gustiness) is measured
+    /// against, in seconds.
+    pub baseline: f32,
+}
+
+impl Default for AudioWindSettings {
+    fn default() -> Self {
+        Self {
+            full_speed: 20.0,
+            smoothing: 0.15,
+            baseline: 3.0,
+        }
+    }
+}
+
+/// Wind as heard by the [`WindListener`], smoothed for driving sound parameters such as the
+/// volume, pitch, and panning of a looping wind sample.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct AudioWind {
+    /// Wind speed at the listener in m/s.
+    pub speed: f32,
+    /// Speed relative to [`AudioWindSettings::full_speed`], from `0` to `1`.
+    pub loudness: f32,
+    /// How far the speed strays from its recent average, relative to that average. Steady wind is
+    /// near `0`, and gusts push it towards `1` and beyond.
+    pub gustiness: f32,
+    /// The direction the wind blows towards, in the listener's local space, or zero when calm.
+    /// Its `x` component suits stereo panning.
+    pub direction: Vec3,
+    baseline: f32,
+}
+
+pub struct AudioWindPlugin;
+
+impl Plugin for AudioWindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioWindSettings>()
+            .init_resource::<AudioWind>()
+            .register_type::<WindListener>()
+            .add_systems(Update, update_audio_wind.in_set(VaneSystems::Respond));
+    }
+}
+
+fn update_audio_wind(
+    time: Res<Time>,
+    settings: Res<AudioWindSettings>,
+    listeners: Query<(&GlobalTransform, &VaneSamples), With<WindListener>>,
+    mut audio: ResMut<AudioWind>,
+) {
+    let Some((transform, samples)) = listeners.iter().next() else {
+        return;
+    };
+    let velocity = samples.mean().velocity();
+    let speed = velocity.length();
+    let local = transform.rotation().inverse() * velocity;
+
+    let delta = time.delta_secs();
+    let smooth = |time_constant: f32| 1.0 - (-delta / time_constant.max(f32::EPSILON)).exp();
+    let (fast, slow) = (smooth(settings.smoothing), smooth(settings.baseline));
+
+    audio.baseline += (speed - audio.baseline) * slow;
+    audio.speed += (speed - audio.speed) * fast;
+    audio.loudness = (audio.speed / settings.full_speed).clamp(0.0, 1.0);
+    let gustiness = (speed - audio.baseline).abs() / audio.baseline.max(1.0);
+    audio.gustiness += (gustiness - audio.gustiness) * fast;
+    let direction = audio.direction.lerp(local.normalize_or_zero(), fast);
+    audio.direction = direction.normalize_or_zero();
+}
@@ -0,0 +1,516 @@
+use std::collections::{HashMap, hash_map::Entry};
+
+use bevy_ecs::{
+    entity::{EntityHashMap, EntityHashSet},
+    entity_disabling::Disabled,
+    prelude::{
+        Added, Changed, Component, DetectChanges, DetectChangesMut, Entity, Has, Or, Query, Ref,
+        RemovedComponents, ResMut, Resource, With,
+    },
+    query::QueryData,
+};
+use bevy_math::{Affine3A, I64Vec3, IVec3, Vec3, Vec3A, bounding::Aabb3d};
+use bevy_transform::components::GlobalTransform;
+
+use crate::{
+    flow::Flow,
+    occluder::WindOccluder,
+    region::Region,
+    vane::{Vane, VaneGradient, VaneJitter, VaneOffsets},
+    volume::FlowVolume,
+};
+
+/// The world-space bounds of a [`Flow`]'s volume, kept up to date by
+/// [`FlowSystems`](crate::flow::FlowSystems).
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct FlowAabb(pub Aabb3d);
+
+impl Default for FlowAabb {
+    fn default() -> Self {
+        Self(Aabb3d::new(Vec3A::ZERO, Vec3A::ZERO))
+    }
+}
+
+/// The world-space bounds of a [`Vane`]'s sample points.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct VaneAabb(pub Aabb3d);
+
+impl Default for VaneAabb {
+    fn default() -> Self {
+        Self(Aabb3d::new(Vec3A::ZERO, Vec3A::ZERO))
+    }
+}
+
+/// The inverse of an entity's [`GlobalTransform`], for taking world-space points into the local
+/// space of flows, regions, and occluders.
+///
+/// Sampling needs it for every flow at every point, so it is cached and only recomputed by
+/// [`FlowSystems`](crate::flow::FlowSystems) when the transform changes.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct WorldToLocal(pub(crate) Affine3A);
+
+impl WorldToLocal {
+    pub fn affine(&self) -> &Affine3A {
+        &self.0
+    }
+}
+
+impl Default for WorldToLocal {
+    fn default() -> Self {
+        Self(Affine3A::IDENTITY)
+    }
+}
+
+/// A uniform grid over the [`FlowAabb`]s of all flows, so sampling a point only visits the flows
+/// whose bounds overlap its cell instead of every flow in the world. [`Region`]s and
+/// [`WindOccluder`] shadows are indexed the same way.
+///
+/// Kept up to date by [`FlowSystems`](crate::flow::FlowSystems), which only moves the entities
+/// that moved, resized, or were added or removed. Entities covering more than
+/// [`max_cells`](Self::max_cells) cells, such as world-spanning weather, are kept in a separate
+/// list that every query visits. Changing the settings reindexes everything.
+#[derive(Resource, Clone, Debug)]
+pub struct FlowGrid {
+    /// The edge length of a cell in meters.
+    pub cell_size: f32,
+    pub max_cells: u32,
+    flows: GridCells,
+    regions: GridCells,
+    occluders: GridCells,
+}
+
+impl Default for FlowGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: 16.0,
+            max_cells: 64,
+            flows: GridCells::default(),
+            regions: GridCells::default(),
+            occluders: GridCells::default(),
+        }
+    }
+}
+
+impl FlowGrid {
+    fn cell(&self, position: Vec3A) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    /// The cells `aabb` overlaps, or `None` if there are too many.
+    fn cells(&self, aabb: &Aabb3d) -> Option<(IVec3, IVec3)> {
+        let (min, max) = (self.cell(aabb.min), self.cell(aabb.max));
+        (cell_count(min, max) <= u64::from(self.max_cells)).then_some((min, max))
+    }
+
+    /// The flows whose bounds may contain the world-space `position`. Each flow appears at most
+    /// once.
+    pub fn query(&self, position: Vec3) -> impl Iterator<Item = Entity> + '_ {
+        self.flows.query(self.cell(position.into()))
+    }
+
+    /// The regions whose bounds may contain the world-space `position`. Each region appears at
+    /// most once.
+    pub fn query_regions(&self, position: Vec3) -> impl Iterator<Item = Entity> + '_ {
+        self.regions.query(self.cell(position.into()))
+    }
+
+    /// The occluders whose shadows may reach the world-space `position`. Each occluder appears
+    /// at most once.
+    pub fn query_occluders(&self, position: Vec3) -> impl Iterator<Item = Entity> + '_ {
+        self.occluders.query(self.cell(position.into()))
+    }
+
+    fn insert(&mut self, entity: Entity, aabb: &Aabb3d) {
+        let cells = self.cells(aabb);
+        self.flows.insert(entity, cells);
+    }
+}
+
+/// The entities in each cell of a [`FlowGrid`].
+#[derive(Clone, Debug, Default)]
+struct GridCells {
+    cells: HashMap<IVec3, Vec<Entity>>,
+    large: Vec<Entity>,
+    /// The cells each entity is in, or `None` for those in `large`.
+    placed: EntityHashMap<Option<(IVec3, IVec3)>>,
+}
+
+impl GridCells {
+    fn query(&self, cell: IVec3) -> impl Iterator<Item = Entity> + '_ {
+        self.cells
+            .get(&cell)
+            .into_iter()
+            .flatten()
+            .chain(&self.large)
+            .copied()
+    }
+
+    fn clear(&mut self) {
+        self.cells.clear();
+        self.large.clear();
+        self.placed.clear();
+    }
+
+    /// Moves `entity` into `cells`, doing nothing if it is already there.
+    fn insert(&mut self, entity: Entity, cells: Option<(IVec3, IVec3)>) {
+        if self.placed.get(&entity) == Some(&cells) {
+            return;
+        }
+        self.remove(entity);
+        match cells {
+            Some((min, max)) => {
+                for cell in cell_range(min, max) {
+                    self.cells.entry(cell).or_default().push(entity);
+                }
+            }
+            None => self.large.push(entity),
+        }
+        self.placed.insert(entity, cells);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(cells) = self.placed.remove(&entity) else {
+            return;
+        };
+        let Some((min, max)) = cells else {
+            self.large.retain(|&other| other != entity);
+            return;
+        };
+        for cell in cell_range(min, max) {
+            if let Entry::Occupied(mut entities) = self.cells.entry(cell) {
+                entities.get_mut().retain(|&other| other != entity);
+                if entities.get().is_empty() {
+                    entities.remove();
+                }
+            }
+        }
+    }
+}
+
+fn cell_range(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+    (min.z..=max.z)
+        .flat_map(move |z| (min.y..=max.y).map(move |y| (y, z)))
+        .flat_map(move |(y, z)| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+}
+
+/// The number of cells from `min` to `max` inclusive, without overflowing for bounds spanning
+/// most of the `i32` range, such as those of huge or infinite volumes.
+pub(crate) fn cell_count(min: IVec3, max: IVec3) -> u64 {
+    let count = (max.as_i64vec3() - min.as_i64vec3() + I64Vec3::ONE)
+        .max(I64Vec3::ZERO)
+        .as_u64vec3();
+    count.x.saturating_mul(count.y).saturating_mul(count.z)
+}
+
+/// Bounds `local` after transforming it by `affine`, which may rotate and scale non-uniformly.
+pub(crate) fn transform_aabb(affine: &Affine3A, local: &Aabb3d) -> Aabb3d {
+    let center = affine.transform_point3a((local.min + local.max) * 0.5);
+    let half_size = (local.max - local.min) * 0.5;
+    let matrix = affine.matrix3;
+    let half_size = matrix.x_axis.abs() * half_size.x
+        + matrix.y_axis.abs() * half_size.y
+        + matrix.z_axis.abs() * half_size.z;
+    Aabb3d {
+        min: center - half_size,
+        max: center + half_size,
+    }
+}
+
+pub(crate) fn update_flow_aabbs(
+    mut flows: Query<
+        (
+            Entity,
+            Ref<GlobalTransform>,
+            Option<Ref<FlowVolume>>,
+            &mut FlowAabb,
+        ),
+        With<Flow>,
+    >,
+    mut removed_volumes: RemovedComponents<FlowVolume>,
+) {
+    let removed: EntityHashSet = removed_volumes.read().collect();
+    for (entity, transform, volume, mut aabb) in &mut flows {
+        let changed = transform.is_changed()
+            || volume.as_ref().is_some_and(DetectChanges::is_changed)
+            || aabb.is_added()
+            || removed.contains(&entity);
+        if !changed {
+            continue;
+        }
+        let volume = volume.as_deref().unwrap_or(&FlowVolume::Box);
+        aabb.0 = volume.world_aabb(&transform.affine());
+    }
+}
+
+pub(crate) fn update_flow_grid(
+    mut grid: ResMut<FlowGrid>,
+    // Disabled flows are indexed too, so re-enabling one needs no reindexing.
+    flows: Query<(Entity, Ref<Flow>, Ref<FlowAabb>, Has<Disabled>)>,
+    mut removed: RemovedComponents<Flow>,
+) {
+    trace_span!("vane::index_flows");
+    let reindex = grid.is_changed();
+    let grid = grid.bypass_change_detection();
+    if reindex {
+        grid.flows.clear();
+    }
+    for entity in removed.read() {
+        grid.flows.remove(entity);
+    }
+    for (entity, flow, aabb, _) in &flows {
+        if reindex || flow.is_added() || aabb.is_changed() {
+            grid.insert(entity, &aabb.0);
+        }
+    }
+}
+
+/// Indexes [`Region`]s by their volumes and [`WindOccluder`]s by the reach of their shadows.
+pub(crate) fn update_region_grid(
+    mut grid: ResMut<FlowGrid>,
+    regions: Query<(Entity, RegionBounds, Has<Disabled>), With<Region>>,
+    occluders: Query<(
+        Entity,
+        Ref<GlobalTransform>,
+        Ref<WindOccluder>,
+        Has<Disabled>,
+    )>,
+    mut removed_regions: RemovedComponents<Region>,
+    mut removed_occluders: RemovedComponents<WindOccluder>,
+    mut removed_volumes: RemovedComponents<FlowVolume>,
+) {
+    trace_span!("vane::index_regions");
+    let reindex = grid.is_changed();
+    let grid = grid.bypass_change_detection();
+    if reindex {
+        grid.regions.clear();
+        grid.occluders.clear();
+    }
+    for entity in removed_regions.read() {
+        grid.regions.remove(entity);
+    }
+    for entity in removed_occluders.read() {
+        grid.occluders.remove(entity);
+    }
+
+    let removed_volumes: EntityHashSet = removed_volumes.read().collect();
+    for (entity, region, _) in &regions {
+        let changed = region.region.is_added()
+            || region.transform.is_changed()
+            || region
+                .volume
+                .as_ref()
+                .is_some_and(DetectChanges::is_changed)
+            || removed_volumes.contains(&entity);
+        if reindex || changed {
+            let volume = region.volume.as_deref().unwrap_or(&FlowVolume::Box);
+            let cells = grid.cells(&volume.world_aabb(&region.transform.affine()));
+            grid.regions.insert(entity, cells);
+        }
+    }
+    for (entity, transform, occluder, _) in &occluders {
+        if reindex || transform.is_changed() || occluder.is_changed() {
+            // The shadow reaches `length` past the occluder in whichever direction the wind blows.
+            let aabb = transform_aabb(&transform.affine(), &FlowVolume::Box.local_aabb());
+            let reach = Vec3A::splat(occluder.length.max(0.0));
+            let shadow = Aabb3d {
+                min: aabb.min - reach,
+                max: aabb.max + reach,
+            };
+            let cells = grid.cells(&shadow);
+            grid.occluders.insert(entity, cells);
+        }
+    }
+}
+
+/// The components of a [`Region`] that place it in the [`FlowGrid`].
+#[derive(QueryData)]
+pub(crate) struct RegionBounds {
+    region: Ref<'static, Region>,
+    transform: Ref<'static, GlobalTransform>,
+    volume: Option<Ref<'static, FlowVolume>>,
+}
+
+pub(crate) fn update_world_to_local(
+    mut entities: Query<
+        (&GlobalTransform, &mut WorldToLocal),
+        Or<(Changed<GlobalTransform>, Added<WorldToLocal>)>,
+    >,
+) {
+    for (transform, mut world_to_local) in &mut entities {
+        world_to_local.0 = transform.affine().inverse();
+    }
+}
+
+pub(crate) fn update_vane_aabbs(
+    mut vanes: Query<
+        (
+            &GlobalTransform,
+            Option<&VaneJitter>,
+            Option<&VaneOffsets>,
+            Option<&VaneGradient>,
+            &mut VaneAabb,
+        ),
+        (
+            With<Vane>,
+            Or<(
+                Changed<GlobalTransform>,
+                Changed<VaneJitter>,
+                Changed<VaneOffsets>,
+                Changed<VaneGradient>,
+                Added<VaneAabb>,
+            )>,
+        ),
+    >,
+) {
+    trace_span!("vane::prepare");
+    for (transform, jitter, offsets, gradient, mut aabb) in &mut vanes {
+        let half_size = Vec3A::from(jitter.map_or(Vec3::ZERO, |jitter| jitter.extent * 0.5));
+        let (min, max) = offsets
+            .iter()
+            .flat_map(|offsets| &offsets.0)
+            .fold((Vec3A::ZERO, Vec3A::ZERO), |(min, max), &offset| {
+                (min.min(offset.into()), max.max(offset.into()))
+            });
+        let local = Aabb3d {
+            min: min - half_size,
+            max: max + half_size,
+        };
+        let world = transform_aabb(&transform.affine(), &local);
+        // Gradient steps are taken along world axes, past the transformed bounds.
+        let step = Vec3A::splat(gradient.map_or(0.0, |gradient| gradient.step));
+        aabb.0 = Aabb3d {
+            min: world.min - step,
+            max: world.max + step,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::FRAC_PI_4;
+
+    use bevy_math::{EulerRot, Quat, bounding::BoundingVolume};
+    use bevy_transform::components::Transform;
+
+    use super::*;
+    use crate::test_utils::FlowWorldBuilder;
+
+    /// The bounds of the transformed corners of `local`, the slow way.
+    fn corner_bounds(affine: &Affine3A, local: &Aabb3d) -> Aabb3d {
+        let (min, max) = (0..8)
+            .map(|corner| {
+                let select =
+                    |bit: u32, min: f32, max: f32| if corner & bit == 0 { min } else { max };
+                affine.transform_point3a(Vec3A::new(
+                    select(1, local.min.x, local.max.x),
+                    select(2, local.min.y, local.max.y),
+                    select(4, local.min.z, local.max.z),
+                ))
+            })
+            .fold(
+                (Vec3A::splat(f32::INFINITY), Vec3A::splat(f32::NEG_INFINITY)),
+                |(min, max), corner| (min.min(corner), max.max(corner)),
+            );
+        Aabb3d { min, max }
+    }
+
+    #[test]
+    fn transformed_aabbs_bound_the_corners() {
+        let affine = Affine3A::from_scale_rotation_translation(
+            Vec3::new(2.0, 1.0, 0.5),
+            Quat::from_euler(EulerRot::XYZ, 0.3, FRAC_PI_4, 1.0),
+            Vec3::new(1.0, -2.0, 3.0),
+        );
+        let local = Aabb3d {
+            min: Vec3A::new(-0.5, -0.25, 0.0),
+            max: Vec3A::new(0.5, 0.5, 0.25),
+        };
+        let aabb = transform_aabb(&affine, &local);
+        let expected = corner_bounds(&affine, &local);
+        assert!(aabb.min.abs_diff_eq(expected.min, 1e-5), "{aabb:?}");
+        assert!(aabb.max.abs_diff_eq(expected.max, 1e-5), "{aabb:?}");
+    }
+
+    #[test]
+    fn flow_aabbs_follow_their_transforms() {
+        let mut world = FlowWorldBuilder::default();
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0)
+            .with_rotation(Quat::from_rotation_z(FRAC_PI_4))
+            .with_scale(Vec3::new(4.0, 1.0, 1.0));
+        let flow = world.uniform_flow(Vec3::X, transform);
+        world.step(1);
+
+        let aabb = world.world().get::<FlowAabb>(flow).unwrap().0;
+        let expected = corner_bounds(
+            &GlobalTransform::from(transform).affine(),
+            &FlowVolume::Box.local_aabb(),
+        );
+        assert!(aabb.min.abs_diff_eq(expected.min, 1e-5), "{aabb:?}");
+        assert!(aabb.max.abs_diff_eq(expected.max, 1e-5), "{aabb:?}");
+
+        world
+            .world_mut()
+            .get_mut::<Transform>(flow)
+            .unwrap()
+            .translation = Vec3::ZERO;
+        world.step(1);
+        let moved = world.world().get::<FlowAabb>(flow).unwrap().0;
+        assert!(moved.center().abs_diff_eq(Vec3A::ZERO, 1e-5), "{moved:?}");
+    }
+
+    #[test]
+    fn huge_flows_skip_the_cells() {
+        let mut grid = FlowGrid::default();
+        let everywhere = Aabb3d {
+            min: Vec3A::splat(f32::NEG_INFINITY),
+            max: Vec3A::splat(f32::INFINITY),
+        };
+        grid.insert(Entity::PLACEHOLDER, &everywhere);
+        assert!(grid.flows.cells.is_empty());
+        assert_eq!(
+            grid.query(Vec3::splat(1e9)).collect::<Vec<_>>(),
+            [Entity::PLACEHOLDER]
+        );
+        assert_eq!(cell_count(IVec3::MIN, IVec3::MAX), u64::MAX);
+        assert_eq!(cell_count(IVec3::ZERO, IVec3::ONE), 8);
+    }
+
+    #[test]
+    fn grids_move_only_what_changed() {
+        let mut world = FlowWorldBuilder::default();
+        let far = Vec3::X * 100.0;
+        let still = world.uniform_flow(Vec3::X, Transform::default());
+        let moving = world.uniform_flow(Vec3::X, Transform::default());
+        let region = world.region(Transform::default());
+        world.step(1);
+        let query = |world: &FlowWorldBuilder, position| {
+            let grid = world.world().resource::<FlowGrid>();
+            let mut flows: Vec<_> = grid.query(position).collect();
+            flows.sort();
+            (flows, grid.query_regions(position).collect::<Vec<_>>())
+        };
+        let mut both = vec![still, moving];
+        both.sort();
+        assert_eq!(query(&world, Vec3::ZERO), (both, vec![region]));
+
+        for entity in [moving, region] {
+            world
+                .world_mut()
+                .get_mut::<Transform>(entity)
+                .unwrap()
+                .translation = far;
+        }
+        world.step(1);
+        assert_eq!(query(&world, Vec3::ZERO), (vec![still], vec![]));
+        assert_eq!(query(&world, far), (vec![moving], vec![region]));
+
+        world.world_mut().despawn(moving);
+        world.world_mut().despawn(region);
+        world.step(1);
+        assert_eq!(query(&world, far), (vec![], vec![]));
+        let grid = world.world().resource::<FlowGrid>();
+        // Empty cells are dropped.
+        assert!(grid.flows.cells.values().all(|flows| flows == &[still]));
+        assert!(grid.regions.cells.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use core::f32::consts::{PI, TAU};
+
+use bevy_asset::Assets;
+use bevy_ecs::{
+    hierarchy::ChildOf,
+    prelude::{Bundle, Commands, Entity, EntityCommands, EntityWorldMut},
+};
+use bevy_math::{UVec3, Vec3};
+use bevy_transform::components::Transform;
+
+use crate::{
+    field::FlowField,
+    flow::{Flow, FlowBlend, FlowFalloff, FlowInfluence, FlowLayers, FlowVector},
+    region::InRegion,
+    vane::Vane,
+    volume::FlowVolume,
+};
+
+pub trait WindVolumeCommandsExt {
+    /// Spawns a [`Flow`] whose field is generated by evaluating `generator` at every texel
+    /// center of a field of `size` texels, in the flow's local unit cube.
+    ///
+    /// The field asset is created when the commands are applied. Use the returned builder to set
+    /// up the rest of the flow.
+    fn spawn_wind_volume(
+        &mut self,
+        size: UVec3,
+        generator: impl FnMut(Vec3) -> FlowVector + Send + 'static,
+    ) -> WindVolumeBuilder<'_>;
+}
+
+impl WindVolumeCommandsExt for Commands<'_, '_> {
+    fn spawn_wind_volume(
+        &mut self,
+        size: UVec3,
+        generator: impl FnMut(Vec3) -> FlowVector + Send + 'static,
+    ) -> WindVolumeBuilder<'_> {
+        let mut entity = self.spawn_empty();
+        entity.queue(move |mut entity: EntityWorldMut| {
+            let field = FlowField::from_fn(size, generator);
+            let field = entity.resource_mut::<Assets<FlowField>>().add(field);
+            entity.insert(Flow::new(field));
+        });
+        WindVolumeBuilder { entity }
+    }
+}
+
+/// Sets up a flow spawned with [`WindVolumeCommandsExt::spawn_wind_volume`].
+pub struct WindVolumeBuilder<'a> {
+    entity: EntityCommands<'a>,
+}
+
+impl<'a> WindVolumeBuilder<'a> {
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.entity.insert(transform);
+        self
+    }
+
+    pub fn layers(mut self, layers: FlowLayers) -> Self {
+        self.entity.insert(layers);
+        self
+    }
+
+    pub fn influence(mut self, influence: f32) -> Self {
+        self.entity.insert(FlowInfluence(influence));
+        self
+    }
+
+    pub fn volume(mut self, volume: FlowVolume) -> Self {
+        self.entity.insert(volume);
+        self
+    }
+
+    pub fn falloff(mut self, falloff: FlowFalloff) -> Self {
+        self.entity.insert(falloff);
+        self
+    }
+
+    pub fn blend(mut self, blend: FlowBlend) -> Self {
+        self.entity.insert(blend);
+        self
+    }
+
+    pub fn in_region(mut self, region: Entity) -> Self {
+        self.entity.insert(InRegion(region));
+        self
+    }
+
+    /// Inserts any other components.
+    pub fn insert(mut self, bundle: impl Bundle) -> Self {
+        self.entity.insert(bundle);
+        self
+    }
+
+    pub fn id(&self) -> Entity {
+        self.entity.id()
+    }
+
+    /// Returns the underlying [`EntityCommands`].
+    pub fn entity(self) -> EntityCommands<'a> {
+        self.entity
+    }
+}
+
+/// How [`VaneArrayCommandsExt::spawn_vane_array`] lays out its vanes around the parent's origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VaneArrayPattern {
+    /// A square grid in the parent's XZ plane, filled row by row.
+    Grid { spacing: f32 },
+    /// Evenly spaced around a circle in the parent's XZ plane.
+    Ring { radius: f32 },
+    /// Spread evenly over a sphere along a Fibonacci spiral.
+    FibonacciSphere { radius: f32 },
+}
+
+impl VaneArrayPattern {
+    /// The position of vane `index` out of `count`, relative to the parent.
+    pub fn position(&self, index: u32, count: u32) -> Vec3 {
+        let (i, n) = (index as f32, count.max(1) as f32);
+        match *self {
+            Self::Grid { spacing } => {
+                let columns = n.sqrt().ceil() as u32;
+                let rows = count.div_ceil(columns.max(1));
+                let cell = Vec3::new((index % columns) as f32, 0.0, (index / columns) as f32);
+                let center = Vec3::new(columns as f32 - 1.0, 0.0, rows as f32 - 1.0) * 0.5;
+                (cell - center) * spacing
+            }
+            Self::Ring { radius } => {
+                let (sin, cos) = (TAU * i / n).sin_cos();
+                Vec3::new(cos, 0.0, sin) * radius
+            }
+            Self::FibonacciSphere { radius } => {
+                let y = if count > 1 {
+                    1.0 - 2.0 * i / (n - 1.0)
+                } else {
+                    0.0
+                };
+                let ring = (1.0 - y * y).max(0.0).sqrt();
+                let (sin, cos) = (PI * (3.0 - 5.0_f32.sqrt()) * i).sin_cos();
+                Vec3::new(cos * ring, y, sin * ring) * radius
+            }
+        }
+    }
+}
+
+pub trait VaneArrayCommandsExt {
+    /// Spawns a parent at `transform` with `count` child [`Vane`]s laid out in `pattern`, such
+    /// as the sensors of an anemometer rig.
+    ///
+    /// Use the returned builder to give every vane the same layers or measures.
+    fn spawn_vane_array(
+        &mut self,
+        transform: Transform,
+        pattern: VaneArrayPattern,
+        count: u32,
+    ) -> VaneArrayBuilder<'_>;
+}
+
+impl VaneArrayCommandsExt for Commands<'_, '_> {
+    fn spawn_vane_array(
+        &mut self,
+        transform: Transform,
+        pattern: VaneArrayPattern,
+        count: u32,
+    ) -> VaneArrayBuilder<'_> {
+        let parent = self.spawn(transform).id();
+        let vanes = (0..count)
+            .map(|index| {
+                let position = pattern.position(index, count);
+                self.spawn((Vane, Transform::from_translation(position), ChildOf(parent)))
+                    .id()
+            })
+            .collect();
+        VaneArrayBuilder {
+            parent: self.entity(parent),
+            vanes,
+        }
+    }
+}
+
+/// Sets up the vanes spawned with [`VaneArrayCommandsExt::spawn_vane_array`].
+pub struct VaneArrayBuilder<'a> {
+    parent: EntityCommands<'a>,
+    vanes: Vec<Entity>,
+}
+
+impl<'a> VaneArrayBuilder<'a> {
+    pub fn layers(self, layers: FlowLayers) -> Self {
+        self.insert_each(layers)
+    }
+
+    /// Inserts a clone of `bundle` on every vane, such as the measures to take.
+    pub fn insert_each(mut self, bundle: impl Bundle + Clone) -> Self {
+        let mut commands = self.parent.commands();
+        for &vane in &self.vanes {
+            commands.entity(vane).insert(bundle.clone());
+        }
+        self
+    }
+
+    /// Inserts components on the parent.
+    pub fn insert(mut self, bundle: impl Bundle) -> Self {
+        self.parent.insert(bundle);
+        self
+    }
+
+    pub fn id(&self) -> Entity {
+        self.parent.id()
+    }
+
+    /// The vanes, in pattern order.
+    pub fn vanes(&self) -> &[Entity] {
+        &self.vanes
+    }
+
+    /// Returns the parent's [`EntityCommands`].
+    pub fn entity(self) -> EntityCommands<'a> {
+        self.parent
+    }
+}
@@ -0,0 +1,193 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{
+    prelude::{Command, Component, Entity, IntoScheduleConfigs, Query, ResMut, World},
+    system::SystemState,
+};
+use bevy_math::UVec3;
+use bevy_transform::components::{GlobalTransform, Transform};
+
+use crate::{
+    error::VaneErrorPolicy, field::FlowField, flow::FlowLayers, sampler::FlowSampler,
+    vane::VaneSystems,
+};
+
+/// Bakes the composed flow inside the entity's unit cube into a [`FlowField`] every frame.
+///
+/// This is how other systems can consume the wind as a plain velocity grid, such as particle
+/// effects advecting leaves and snow. Parent the capture to a camera and scale it to the area
+/// around the view for a camera-centered grid, or give it a region's transform to bake the whole
+/// region. The baked vectors are in world space, sampled at texel centers like any field.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub struct FlowCapture {
+    pub size: UVec3,
+    /// The layers to capture. Defaults to all of them, like a [`Vane`](crate::vane::Vane).
+    pub layers: FlowLayers,
+    field: Option<Handle<FlowField>>,
+    baked: Option<FlowField>,
+}
+
+impl FlowCapture {
+    pub fn new(size: UVec3) -> Self {
+        Self {
+            size,
+            layers: FlowLayers::all(),
+            field: None,
+            baked: None,
+        }
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// The field holding the latest capture, once the first one has been taken.
+    pub fn field(&self) -> Option<&Handle<FlowField>> {
+        self.field.as_ref()
+    }
+}
+
+/// Flattens all of a [`Region`](crate::region::Region)'s flows into a single static field over
+/// the region's unit cube, inserted on the region as a [`BakedRegion`].
+///
+/// Flows are composed with their influence, layers, and transforms as they are when the command
+/// runs. The flows of regions nested in the region are baked in inside their own volumes. The
+/// ambient flow, other regions' flows, and occluders are left out, so a [`Flow`] with the baked
+/// field and the region's transform can stand in for the region's flows.
+/// Missing regions are reported according to the [`VaneErrorPolicy`].
+///
+/// [`Flow`]: crate::flow::Flow
+#[derive(Clone, Debug)]
+pub struct BakeRegion {
+    pub region: Entity,
+    pub resolution: UVec3,
+    pub layers: FlowLayers,
+}
+
+impl BakeRegion {
+    pub fn new(region: Entity, resolution: UVec3) -> Self {
+        Self {
+            region,
+            resolution,
+            layers: FlowLayers::all(),
+        }
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+impl Command for BakeRegion {
+    fn apply(self, world: &mut World) {
+        let mut state = SystemState::<(FlowSampler, Query<&GlobalTransform>)>::new(world);
+        let (sampler, transforms) = state.get(world);
+        let Ok(transform) = transforms.get(self.region) else {
+            let policy = world
+                .get_resource::<VaneErrorPolicy>()
+                .copied()
+                .unwrap_or_default();
+            policy.report(format_args!("cannot bake missing region {}", self.region));
+            return;
+        };
+        let field = FlowField::from_fn(self.resolution, |local| {
+            let position = transform.transform_point(local);
+            sampler.sample_region(self.region, position, self.layers)
+        });
+        let field = world.resource_mut::<Assets<FlowField>>().add(field);
+        world.entity_mut(self.region).insert(BakedRegion(field));
+    }
+}
+
+/// The field a [`BakeRegion`] baked this region's flows into.
+#[derive(Component, Clone, Debug)]
+pub struct BakedRegion(pub Handle<FlowField>);
+
+pub struct FlowCapturePlugin;
+
+impl Plugin for FlowCapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (bake_captures, store_captures)
+                .chain()
+                .in_set(VaneSystems::Sample),
+        );
+    }
+}
+
+fn bake_captures(sampler: FlowSampler, mut captures: Query<(&GlobalTransform, &mut FlowCapture)>) {
+    trace_span!("vane::bake_captures");
+    captures
+        .par_iter_mut()
+        .for_each(|(transform, mut capture)| {
+            let affine = transform.affine();
+            let layers = capture.layers;
+            let mut baked = match capture.baked.take() {
+                Some(field) if field.size() == capture.size => field,
+                _ => FlowField::new(capture.size),
+            };
+            baked.fill(|local| sampler.sample(affine.transform_point3(local), layers));
+            capture.baked = Some(baked);
+        });
+}
+
+/// Moves the baked grids into their assets, which the sampler couldn't borrow mutably.
+fn store_captures(mut captures: Query<&mut FlowCapture>, mut fields: ResMut<Assets<FlowField>>) {
+    trace_span!("vane::readback_captures");
+    for mut capture in &mut captures {
+        let Some(baked) = capture.baked.take() else {
+            continue;
+        };
+        match &capture.field {
+            Some(field) if fields.contains(field) => {
+                let previous = fields
+                    .get_mut(field)
+                    .map(|field| core::mem::replace(field, baked));
+                capture.baked = previous;
+            }
+            _ => capture.field = Some(fields.add(baked)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::hierarchy::ChildOf;
+    use bevy_math::Vec3;
+
+    use super::*;
+    use crate::test_utils::FlowWorldBuilder;
+
+    #[test]
+    fn baked_regions_include_nested_regions() {
+        let mut world = FlowWorldBuilder::default();
+        let wide = Transform::from_scale(Vec3::splat(10.0));
+        let region = world.region(Transform::from_scale(Vec3::new(4.0, 1.0, 1.0)));
+        world.uniform_flow_in(region, Vec3::X * 3.0, wide);
+        // Covers the half of the region along +X.
+        let nested = world.region(Transform::from_xyz(0.25, 0.0, 0.0).with_scale(Vec3::splat(0.5)));
+        world.world_mut().entity_mut(nested).insert(ChildOf(region));
+        world.uniform_flow_in(nested, Vec3::Y * 3.0, wide);
+        let other = world.region(wide);
+        world.uniform_flow_in(other, Vec3::Z * 3.0, wide);
+        world.step(1);
+
+        BakeRegion::new(region, UVec3::new(4, 1, 1)).apply(world.world_mut());
+        let world = world.world();
+        let baked = world.get::<BakedRegion>(region).unwrap();
+        let field = world.resource::<Assets<FlowField>>().get(&baked.0).unwrap();
+        for (x, expected) in [
+            (-0.375, Vec3::X * 3.0),
+            (-0.125, Vec3::X * 3.0),
+            (0.125, Vec3::new(1.5, 1.5, 0.0)),
+            (0.375, Vec3::new(1.5, 1.5, 0.0)),
+        ] {
+            let wind = field.sample(Vec3::X * x).velocity();
+            assert!(wind.abs_diff_eq(expected, 1e-4), "baked {wind} at {x}");
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Coverage statistics for [`Region`]s, for finding dead zones and hot spots in large wind setups.
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::{Component, IntoScheduleConfigs, Query, Res, With};
+use bevy_math::UVec3;
+use bevy_time::Time;
+use bevy_transform::components::GlobalTransform;
+
+use crate::{
+    bounds::WorldToLocal,
+    flow::FlowLayers,
+    region::{self, Region},
+    sampler::FlowSampler,
+    vane::VaneSystems,
+    volume::FlowVolume,
+};
+
+/// Periodically samples a grid of points over a [`Region`] and summarizes the flow there in
+/// [`RegionCoverageStats`].
+///
+/// Each survey samples the flow twice per grid point, so keep `resolution` and the rate modest.
+#[derive(Component, Clone, Debug)]
+#[require(Region, RegionCoverageStats)]
+pub struct RegionCoverage {
+    /// Grid points along each axis of the region's unit cube.
+    pub resolution: UVec3,
+    pub layers: FlowLayers,
+    /// Seconds between surveys.
+    pub interval: f32,
+    until_next: f32,
+}
+
+impl RegionCoverage {
+    pub fn new(resolution: UVec3) -> Self {
+        Self {
+            resolution,
+            layers: FlowLayers::all(),
+            interval: 1.0,
+            until_next: 0.0,
+        }
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: f32) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+/// The result of the latest [`RegionCoverage`] survey.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RegionCoverageStats {
+    /// Grid points inside the region.
+    pub points: u32,
+    /// Points where at least one flow contributes, not counting the ambient flow.
+    pub covered: u32,
+    /// The mean weighted influence of the contributing flows over all points.
+    pub mean_influence: f32,
+    /// The highest composed wind speed at any point, in m/s.
+    pub max_speed: f32,
+}
+
+impl RegionCoverageStats {
+    /// The fraction of points covered by a flow, or zero if there are none.
+    pub fn covered_fraction(&self) -> f32 {
+        if self.points == 0 {
+            return 0.0;
+        }
+        self.covered as f32 / self.points as f32
+    }
+}
+
+pub struct RegionCoveragePlugin;
+
+impl Plugin for RegionCoveragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, survey_regions.in_set(VaneSystems::Sample));
+    }
+}
+
+fn survey_regions(
+    time: Res<Time>,
+    sampler: FlowSampler,
+    mut regions: Query<
+        (
+            &GlobalTransform,
+            &WorldToLocal,
+            Option<&FlowVolume>,
+            &mut RegionCoverage,
+            &mut RegionCoverageStats,
+        ),
+        With<Region>,
+    >,
+) {
+    trace_span!("vane::survey_regions");
+    for (transform, world_to_local, volume, mut coverage, mut stats) in &mut regions {
+        coverage.until_next -= time.delta_secs();
+        if coverage.until_next > 0.0 {
+            continue;
+        }
+        coverage.until_next = coverage.interval;
+
+        let resolution = coverage.resolution.max(UVec3::ONE);
+        let mut survey = RegionCoverageStats::default();
+        let mut influence = 0.0;
+        for z in 0..resolution.z {
+            for y in 0..resolution.y {
+                for x in 0..resolution.x {
+                    let local = (UVec3::new(x, y, z).as_vec3() + 0.5) / resolution.as_vec3() - 0.5;
+                    let position = transform.transform_point(local);
+                    if !region::contains(world_to_local, volume, position) {
+                        continue;
+                    }
+                    survey.points += 1;
+                    let (flows, weight) = sampler.coverage(position, coverage.layers);
+                    if flows > 0 {
+                        survey.covered += 1;
+                    }
+                    influence += weight;
+                    let speed = sampler
+                        .sample(position, coverage.layers)
+                        .velocity()
+                        .length();
+                    survey.max_speed = survey.max_speed.max(speed);
+                }
+            }
+        }
+        survey.mean_influence = influence / survey.points.max(1) as f32;
+        *stats = survey;
+    }
+}
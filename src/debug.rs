@@ -0,0 +1,544 @@
+//! Gizmos for authoring flows and placing vanes, behind the `debug-gizmos` feature.
+//!
+//! Mark flows with [`DebugFlow`] and vanes with [`DebugVane`] to draw them, and vanes with a
+//! [`DebugMeasure`] to draw their latest measured value. [`DebugStreamlines`] on an
+//! [`ActiveRegion`] show how the composed flow carries things through it. Regions and spatial
+//! activity are drawn as set in [`DebugActivity`].
+//!
+//! Everything is drawn through the [`VaneGizmos`] config group, which can be turned off or
+//! restyled as a whole in the [`GizmoConfigStore`](bevy_gizmos::config::GizmoConfigStore).
+//! Nothing shows up without Bevy's `GizmoPlugin`.
+
+use core::{fmt, marker::PhantomData};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_color::{
+    Alpha, Color,
+    palettes::css::{AQUA, GRAY, LIME, ORANGE, RED, WHITE, YELLOW},
+};
+use bevy_ecs::{
+    prelude::{Component, Has, IntoScheduleConfigs, Query, ReflectComponent, Res, Resource, With},
+    relationship::RelationshipTarget,
+};
+use bevy_gizmos::{AppGizmoBuilder, config::GizmoConfigGroup, gizmos::Gizmos};
+use bevy_math::{
+    Isometry3d, UVec3, Vec3, Vec3A,
+    bounding::{Aabb3d, BoundingVolume},
+};
+use bevy_reflect::Reflect;
+use bevy_transform::components::{GlobalTransform, Transform};
+
+use crate::{
+    activity::{Active, ActiveRegion, ActivityData, ActivityTier, SpatialActivity, is_inactive},
+    bounds::{FlowAabb, VaneAabb, transform_aabb},
+    flow::{Flow, FlowLayers},
+    measure::{Measure, Measured},
+    region::{Contains, Region},
+    sampler::FlowSampler,
+    vane::{Vane, VaneSampleAge, VaneSamples, VaneSystems},
+    visibility::{VisibilityData, is_hidden},
+};
+
+/// The gizmo config group for everything drawn by [`VaneDebugPlugin`].
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct VaneGizmos;
+
+/// Draws the composed flow inside a [`Flow`]'s bounds as arrows on a lattice.
+///
+/// Arrows point along the momentum and are colored by speed, from blue when calm to red at
+/// [`max_speed`](Self::max_speed). Hidden flows and flows outside every active region are not
+/// drawn.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct DebugFlow {
+    /// The distance between lattice points in meters. It is widened for large flows so no more
+    /// than [`DebugFlow::MAX_POINTS`] arrows are drawn.
+    pub spacing: f32,
+    /// The speed drawn in red, in m/s.
+    pub max_speed: f32,
+    /// The layers to sample, or the flow's own if `None`.
+    pub layers: Option<FlowLayers>,
+}
+
+impl DebugFlow {
+    pub const MAX_POINTS: u32 = 4096;
+
+    pub fn with_spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_max_speed(mut self, max_speed: f32) -> Self {
+        self.max_speed = max_speed;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = Some(layers);
+        self
+    }
+}
+
+impl Default for DebugFlow {
+    fn default() -> Self {
+        Self {
+            spacing: 2.0,
+            max_speed: 20.0,
+            layers: None,
+        }
+    }
+}
+
+/// Draws a [`Vane`]'s sample positions and the velocity sampled at each.
+///
+/// Positions are colored by their [`VaneSampleAge`], from green when taken this frame to red at
+/// [`max_age`](Self::max_age), and velocity arrows by speed as for [`DebugFlow`]. Vanes that are
+/// not sampling have no samples to draw.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+#[require(VaneSampleAge)]
+pub struct DebugVane {
+    /// The seconds of travel each velocity arrow spans, so an arrow of length `1.0` at the
+    /// default means 4 m/s.
+    pub scale: f32,
+    /// The speed drawn in red, in m/s.
+    pub max_speed: f32,
+    /// The sample age drawn in red, in seconds.
+    pub max_age: f32,
+}
+
+impl Default for DebugVane {
+    fn default() -> Self {
+        Self {
+            scale: 0.25,
+            max_speed: 20.0,
+            max_age: 0.25,
+        }
+    }
+}
+
+/// Traces streamlines of the composed flow from a lattice of seeds over an [`ActiveRegion`]'s
+/// unit cube and draws them as polylines colored by speed.
+///
+/// Lines are retraced every [`interval`](Self::interval) frames and drawn from the last trace in
+/// between. Each follows the flow in fixed steps until it runs out of steps or reaches still air,
+/// so its length shows how far the flow reaches rather than how fast it is.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct DebugStreamlines {
+    /// Seeds along each axis of the region's unit cube.
+    pub seeds: UVec3,
+    pub steps: u32,
+    /// The distance covered by each step, in meters.
+    pub step_length: f32,
+    /// Frames between traces.
+    pub interval: u32,
+    /// The speed drawn in red, in m/s.
+    pub max_speed: f32,
+    /// The layers to trace, or the region's own if `None`.
+    pub layers: Option<FlowLayers>,
+    #[reflect(ignore)]
+    lines: Vec<Vec<(Vec3, Color)>>,
+    #[reflect(ignore)]
+    until_next: u32,
+}
+
+impl DebugStreamlines {
+    pub fn new(seeds: UVec3) -> Self {
+        Self {
+            seeds,
+            steps: 32,
+            step_length: 0.5,
+            interval: 10,
+            max_speed: 20.0,
+            layers: None,
+            lines: Vec::new(),
+            until_next: 0,
+        }
+    }
+
+    pub fn with_steps(mut self, steps: u32, step_length: f32) -> Self {
+        self.steps = steps;
+        self.step_length = step_length;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: u32) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = Some(layers);
+        self
+    }
+
+    /// The points of each line traced last.
+    pub fn lines(&self) -> impl Iterator<Item = impl Iterator<Item = Vec3> + '_> + '_ {
+        self.lines
+            .iter()
+            .map(|line| line.iter().map(|&(position, _)| position))
+    }
+}
+
+impl Default for DebugStreamlines {
+    fn default() -> Self {
+        Self::new(UVec3::new(4, 2, 4))
+    }
+}
+
+/// What the [`VaneDebugPlugin`] draws of regions and spatial activity. Everything is drawn by
+/// default.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct DebugActivity {
+    /// Draws each [`Region`]'s unit cube in green while any of its flows takes part in sampling,
+    /// gray while none do, and red when it has no flows at all.
+    pub regions: bool,
+    /// Draws the bounds of each [`ActiveRegion`] in cyan, with a fainter box at its margin.
+    pub active_regions: bool,
+    /// Draws the bounds of [`SpatialActivity`] flows and vanes by [`ActivityTier`] while active,
+    /// from green to yellow to orange, and in gray while not.
+    pub entities: bool,
+}
+
+impl Default for DebugActivity {
+    fn default() -> Self {
+        Self {
+            regions: true,
+            active_regions: true,
+            entities: true,
+        }
+    }
+}
+
+/// A measure output that can be drawn with gizmos at a vane.
+pub trait GizmoValue {
+    fn draw(&self, gizmos: &mut Gizmos<VaneGizmos>, origin: Vec3, scale: f32, color: Color);
+}
+
+/// Drawn as a vertical bar, pointing down for negative values.
+impl GizmoValue for f32 {
+    fn draw(&self, gizmos: &mut Gizmos<VaneGizmos>, origin: Vec3, scale: f32, color: Color) {
+        gizmos.line(origin, origin + Vec3::Y * *self * scale, color);
+    }
+}
+
+/// Drawn as an arrow from the vane.
+impl GizmoValue for Vec3 {
+    fn draw(&self, gizmos: &mut Gizmos<VaneGizmos>, origin: Vec3, scale: f32, color: Color) {
+        gizmos.arrow(origin, origin + *self * scale, color);
+    }
+}
+
+/// Draws the latest [`Measured<M>`] value of a vane, once a [`DebugMeasurePlugin<M>`] is added.
+#[derive(Component)]
+pub struct DebugMeasure<M> {
+    pub scale: f32,
+    pub color: Color,
+    marker: PhantomData<M>,
+}
+
+impl<M> DebugMeasure<M> {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            color: WHITE.into(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn with_color(mut self, color: impl Into<Color>) -> Self {
+        self.color = color.into();
+        self
+    }
+}
+
+impl<M> Default for DebugMeasure<M> {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl<M> Clone for DebugMeasure<M> {
+    fn clone(&self) -> Self {
+        Self {
+            scale: self.scale,
+            color: self.color,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M> fmt::Debug for DebugMeasure<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DebugMeasure")
+            .field("scale", &self.scale)
+            .field("color", &self.color)
+            .finish()
+    }
+}
+
+/// Draws [`DebugMeasure<M>`]s. Needs the [`VaneDebugPlugin`].
+pub struct DebugMeasurePlugin<M>(PhantomData<M>);
+
+impl<M> Default for DebugMeasurePlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Measure<Output: GizmoValue>> Plugin for DebugMeasurePlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, draw_measures::<M>.after(VaneSystems::Measure));
+    }
+}
+
+/// Draws [`DebugFlow`]s, [`DebugVane`]s, [`DebugStreamlines`], and [`DebugActivity`] with
+/// gizmos.
+///
+/// It is not part of [`VanePlugins`](crate::VanePlugins), so it can be left out of release
+/// builds.
+pub struct VaneDebugPlugin;
+
+impl Plugin for VaneDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<VaneGizmos>()
+            .init_resource::<DebugActivity>()
+            .register_type::<DebugFlow>()
+            .register_type::<DebugVane>()
+            .register_type::<DebugStreamlines>()
+            .add_systems(
+                PostUpdate,
+                (draw_flows, draw_vanes, draw_streamlines, draw_activity)
+                    .after(VaneSystems::Measure),
+            );
+    }
+}
+
+/// Blue at rest through green to red at `max_speed`.
+fn speed_color(speed: f32, max_speed: f32) -> Color {
+    let t = (speed / max_speed.max(f32::EPSILON)).clamp(0.0, 1.0);
+    Color::hsl(240.0 * (1.0 - t), 0.9, 0.5)
+}
+
+/// Green when fresh through yellow to red at `max_age`.
+fn age_color(age: f32, max_age: f32) -> Color {
+    let t = (age / max_age.max(f32::EPSILON)).clamp(0.0, 1.0);
+    Color::hsl(120.0 * (1.0 - t), 0.9, 0.5)
+}
+
+fn draw_flows(
+    sampler: FlowSampler,
+    flows: Query<
+        (
+            &DebugFlow,
+            &FlowAabb,
+            &FlowLayers,
+            VisibilityData,
+            ActivityData,
+        ),
+        With<Flow>,
+    >,
+    mut gizmos: Gizmos<VaneGizmos>,
+) {
+    trace_span!("vane::draw_flows");
+    for (debug, aabb, layers, visibility, activity) in &flows {
+        if is_hidden(visibility) || is_inactive(activity) {
+            continue;
+        }
+        let layers = debug.layers.unwrap_or(*layers);
+        let extent = Vec3::from(aabb.0.max - aabb.0.min);
+        // Unbounded flows have no lattice to draw.
+        if !extent.is_finite() {
+            continue;
+        }
+        let max_points = DebugFlow::MAX_POINTS as f32;
+        let lattice = |spacing: f32| {
+            (extent / spacing)
+                .as_uvec3()
+                .min(UVec3::splat(DebugFlow::MAX_POINTS))
+                + UVec3::ONE
+        };
+        let mut spacing = debug.spacing.max(0.01);
+        let mut count = lattice(spacing);
+        // Widening by the cube root suits boxy flows, and the rest catches flat and thin ones.
+        for exponent in [1.0 / 3.0, 1.0] {
+            let points = count.as_vec3().element_product();
+            if points <= max_points {
+                break;
+            }
+            spacing *= (points / max_points).powf(exponent);
+            count = lattice(spacing);
+        }
+        let start = Vec3::from(aabb.0.min + aabb.0.max) * 0.5
+            - (count - UVec3::ONE).as_vec3() * spacing * 0.5;
+        for z in 0..count.z {
+            for y in 0..count.y {
+                for x in 0..count.x {
+                    let position = start + UVec3::new(x, y, z).as_vec3() * spacing;
+                    let sample = sampler.sample(position, layers);
+                    let Some(direction) = sample.momentum.try_normalize() else {
+                        continue;
+                    };
+                    let color = speed_color(sample.velocity().length(), debug.max_speed);
+                    let half = direction * spacing * 0.4;
+                    gizmos.arrow(position - half, position + half, color);
+                }
+            }
+        }
+    }
+}
+
+fn draw_vanes(
+    vanes: Query<(&DebugVane, &VaneSamples, &VaneSampleAge), With<Vane>>,
+    mut gizmos: Gizmos<VaneGizmos>,
+) {
+    trace_span!("vane::draw_vanes");
+    for (debug, samples, age) in &vanes {
+        let age_color = age_color(age.secs(), debug.max_age);
+        for sample in &samples.0 {
+            let velocity = sample.flow.velocity();
+            gizmos.sphere(
+                Isometry3d::from_translation(sample.position),
+                0.05,
+                age_color,
+            );
+            gizmos.arrow(
+                sample.position,
+                sample.position + velocity * debug.scale,
+                speed_color(velocity.length(), debug.max_speed),
+            );
+        }
+    }
+}
+
+fn draw_measures<M: Measure<Output: GizmoValue>>(
+    vanes: Query<(&DebugMeasure<M>, &GlobalTransform, &Measured<M>)>,
+    mut gizmos: Gizmos<VaneGizmos>,
+) {
+    for (debug, transform, measured) in &vanes {
+        measured.0.draw(
+            &mut gizmos,
+            transform.translation(),
+            debug.scale,
+            debug.color,
+        );
+    }
+}
+
+fn draw_streamlines(
+    sampler: FlowSampler,
+    mut regions: Query<(&ActiveRegion, &GlobalTransform, &mut DebugStreamlines)>,
+    mut gizmos: Gizmos<VaneGizmos>,
+) {
+    trace_span!("vane::draw_streamlines");
+    for (region, transform, mut streamlines) in &mut regions {
+        let streamlines = &mut *streamlines;
+        if streamlines.until_next == 0 {
+            streamlines.until_next = streamlines.interval.max(1);
+            let layers = streamlines.layers.unwrap_or(region.layers);
+            let seeds = streamlines.seeds.max(UVec3::ONE);
+            streamlines.lines.clear();
+            for z in 0..seeds.z {
+                for y in 0..seeds.y {
+                    for x in 0..seeds.x {
+                        let local = (UVec3::new(x, y, z).as_vec3() + 0.5) / seeds.as_vec3() - 0.5;
+                        let mut position = transform.transform_point(local);
+                        let mut line = Vec::new();
+                        for _ in 0..=streamlines.steps {
+                            let velocity = sampler.sample(position, layers).velocity();
+                            let color = speed_color(velocity.length(), streamlines.max_speed);
+                            line.push((position, color));
+                            // Midpoint steps, so lines bend with curling flow instead of
+                            // drifting outwards.
+                            let Some(direction) = velocity.try_normalize() else {
+                                break;
+                            };
+                            let half_step = streamlines.step_length * 0.5;
+                            let midpoint = sampler
+                                .sample(position + direction * half_step, layers)
+                                .velocity();
+                            position += midpoint.normalize_or(direction) * streamlines.step_length;
+                        }
+                        streamlines.lines.push(line);
+                    }
+                }
+            }
+        }
+        streamlines.until_next -= 1;
+        for line in &streamlines.lines {
+            gizmos.linestrip_gradient(line.iter().copied());
+        }
+    }
+}
+
+fn draw_activity(
+    settings: Res<DebugActivity>,
+    regions: Query<(&GlobalTransform, Option<&Contains>), With<Region>>,
+    flows: Query<ActivityData, With<Flow>>,
+    active_regions: Query<(&ActiveRegion, &GlobalTransform)>,
+    entities: Query<
+        (
+            Option<&FlowAabb>,
+            Option<&VaneAabb>,
+            Has<Active>,
+            Option<&ActivityTier>,
+        ),
+        With<SpatialActivity>,
+    >,
+    mut gizmos: Gizmos<VaneGizmos>,
+) {
+    trace_span!("vane::draw_activity");
+    if settings.regions {
+        for (transform, contains) in &regions {
+            let mut members = contains
+                .iter()
+                .flat_map(|contains| contains.collection())
+                .filter_map(|&flow| flows.get(flow).ok())
+                .peekable();
+            let color = if members.peek().is_none() {
+                RED
+            } else if members.any(|activity| !is_inactive(activity)) {
+                LIME
+            } else {
+                GRAY
+            };
+            gizmos.cuboid(*transform, color);
+        }
+    }
+    if settings.active_regions {
+        for (region, transform) in &active_regions {
+            let unit = Aabb3d::new(Vec3A::ZERO, Vec3A::splat(0.5));
+            let aabb = transform_aabb(&transform.affine(), &unit);
+            draw_aabb(&mut gizmos, &aabb, AQUA.into());
+            draw_aabb(
+                &mut gizmos,
+                &aabb.grow(Vec3A::splat(region.margin)),
+                AQUA.with_alpha(0.3).into(),
+            );
+        }
+    }
+    if settings.entities {
+        for (flow_aabb, vane_aabb, active, tier) in &entities {
+            let color = match (active, tier.copied().unwrap_or_default()) {
+                (false, _) => GRAY,
+                (true, ActivityTier::Active) => LIME,
+                (true, ActivityTier::Reduced) => YELLOW,
+                (true, ActivityTier::Dormant) => ORANGE,
+            };
+            let aabbs = flow_aabb
+                .map(|aabb| aabb.0)
+                .into_iter()
+                .chain(vane_aabb.map(|aabb| aabb.0));
+            for aabb in aabbs {
+                draw_aabb(&mut gizmos, &aabb, color.into());
+            }
+        }
+    }
+}
+
+fn draw_aabb(gizmos: &mut Gizmos<VaneGizmos>, aabb: &Aabb3d, color: Color) {
+    let transform =
+        Transform::from_translation(aabb.center().into()).with_scale((aabb.max - aabb.min).into());
+    gizmos.cuboid(transform, color);
+}
@@ -0,0 +1,374 @@
+use core::f32::consts::TAU;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    hierarchy::ChildOf,
+    prelude::{Component, IntoScheduleConfigs, Query, ReflectComponent, Res, Trigger, Without},
+};
+use bevy_math::{Dir3, Quat, Vec3};
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+use bevy_time::Time;
+use bevy_transform::components::{GlobalTransform, Transform};
+
+use crate::{
+    region::OriginRebased,
+    units::VaneUnits,
+    vane::{Vane, VaneSamples, VaneSystems},
+};
+
+/// Moves a transform-only entity along with the flow sampled by its [`Vane`].
+///
+/// Each frame the quadratic drag of the relative flow is integrated into the entity's
+/// [`BodyVelocity`], which then moves its [`Transform`]. No physics engine is involved, making
+/// this suitable for cheap debris like leaves, paper, and embers. Intended for unparented entities.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Vane, BodyVelocity)]
+pub struct FlowDriven {
+    /// Mass of the entity in kilograms.
+    pub mass: f32,
+    /// Reference area for drag in square meters.
+    pub drag_area: f32,
+    /// Drag coefficient.
+    pub cd: f32,
+}
+
+/// World-space linear velocity of an entity carrying a [`Vane`].
+///
+/// Consumers use it to find the flow relative to the entity. [`FlowDriven`] integrates it
+/// itself; for other entities it should be kept in sync with whatever moves them.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub struct BodyVelocity(pub Vec3);
+
+/// Smoothly rotates an entity so that its local `axis` points along the flow sampled by its
+/// [`Vane`], for flags, windsocks, smoke columns, and weathervanes.
+///
+/// Each frame the rotation closes `1 - e^(-responsiveness * dt)` of the remaining angle. The
+/// rotation is left alone while the flow is still.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Vane)]
+pub struct FaceFlow {
+    /// The local axis to align with the flow direction.
+    pub axis: Dir3,
+    /// How quickly the entity turns to face the flow, in inverse seconds.
+    pub responsiveness: f32,
+}
+
+/// Sways an entity around its rest pose in response to the flow sampled by its [`Vane`], for
+/// lanterns, signs, and hanging ropes that should react to wind without a physics body.
+///
+/// The entity leans downwind by up to half of `max_angle` and `max_offset` in proportion to the
+/// flow speed, and oscillates around that lean by up to the other half in proportion to the
+/// speed and its turbulence. Both saturate at `full_speed`, so the sway stays bounded.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Vane, FlowSwayState)]
+pub struct FlowSway {
+    /// Maximum rotation away from the rest pose in radians.
+    pub max_angle: f32,
+    /// Maximum translation away from the rest pose.
+    pub max_offset: f32,
+    /// Flow speed at which the sway saturates.
+    pub full_speed: f32,
+    /// Oscillation frequency in hertz.
+    pub frequency: f32,
+}
+
+/// Tracking state for [`FlowSway`] and [`WindSprite2d`].
+///
+/// The rest pose is captured from the entity's [`Transform`] the first time it sways. Set `rest`
+/// to move a swaying entity.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct FlowSwayState {
+    pub rest: Option<Transform>,
+    /// Smoothed flow velocity.
+    pub velocity: Vec3,
+    /// Smoothed mean absolute deviation of the flow speed.
+    pub turbulence: f32,
+    pub phase: f32,
+}
+
+/// Leans and nudges a 2D sprite with the flow in the XY plane, for reactive foliage and banners in
+/// side-scrollers without a custom shader.
+///
+/// Like [`FlowSway`], but the sprite rotates about `Z` with its top leaning downwind and shifts
+/// along the flow's XY direction. The `Z` component of the flow is ignored.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Vane, FlowSwayState)]
+pub struct WindSprite2d {
+    /// Maximum lean away from the rest pose in radians.
+    pub max_angle: f32,
+    /// Maximum translation away from the rest pose.
+    pub max_offset: f32,
+    /// Flow speed at which the lean saturates.
+    pub full_speed: f32,
+    /// Flutter frequency in hertz.
+    pub frequency: f32,
+}
+
+/// Converts the flow sampled by a [`Vane`] into a push for kinematic character controllers.
+///
+/// The push is the quadratic drag of the flow relative to the [`BodyVelocity`], if any, clamped
+/// to `max_push`. While `grounded`, static friction cancels up to `ground_friction * g` of the
+/// horizontal push and any downward push is dropped. The result is written to [`WindPushOutput`]
+/// for the controller to apply.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Vane, WindPushOutput)]
+pub struct WindPush {
+    /// Mass of the character in kilograms.
+    pub mass: f32,
+    /// Drag coefficient times reference area, in square meters.
+    pub drag_area: f32,
+    /// Maximum acceleration the flow can impart, in meters per second squared.
+    pub max_push: f32,
+    /// Coefficient of friction against the ground.
+    pub ground_friction: f32,
+    /// Whether the character is standing on the ground, kept up to date by the controller.
+    pub grounded: bool,
+}
+
+/// The push computed for a [`WindPush`], updated every frame.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindPushOutput {
+    /// Acceleration to add to the controller's velocity, in meters per second squared.
+    pub acceleration: Vec3,
+    /// `acceleration` applied over the last frame as a displacement, for controllers that only
+    /// move by translation.
+    ///
+    /// This is one semi-implicit Euler step from rest, `acceleration * dt²`, rather than the exact
+    /// `0.5 * acceleration * dt²`. No velocity carries over between frames, so a steady push moves
+    /// the character at a steady `acceleration * dt` meters per second.
+    pub displacement: Vec3,
+}
+
+/// Produces a steering acceleration that makes an agent follow, or fight, the flow sampled by its
+/// [`Vane`], for birds, fish, and drifting AI.
+///
+/// The acceleration is `weight * (flow velocity - BodyVelocity)`, clamped to `max_accel`, and is
+/// written to [`FlowSteeringOutput`] to be blended with the agent's other steering behaviors.
+/// Positive weights steer the agent to match the flow, negative weights steer against it.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Vane, FlowSteeringOutput)]
+pub struct FlowSteering {
+    /// Steering gain in inverse seconds.
+    pub weight: f32,
+    /// Maximum steering acceleration in meters per second squared.
+    pub max_accel: f32,
+}
+
+/// The steering computed for a [`FlowSteering`], updated every frame.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FlowSteeringOutput {
+    pub acceleration: Vec3,
+}
+
+/// Standard gravity used for ground friction, in meters per second squared.
+const GRAVITY: f32 = 9.80665;
+
+pub struct DrivePlugin;
+
+impl Plugin for DrivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VaneUnits>()
+            .register_type::<FlowDriven>()
+            .register_type::<BodyVelocity>()
+            .register_type::<FaceFlow>()
+            .register_type::<FlowSway>()
+            .register_type::<WindSprite2d>()
+            .register_type::<WindPush>()
+            .register_type::<FlowSteering>()
+            .add_systems(
+                Update,
+                (
+                    integrate_flow_driven,
+                    face_flow,
+                    sway,
+                    sway_sprites,
+                    push_characters,
+                    steer_with_flow,
+                )
+                    .in_set(VaneSystems::Respond),
+            )
+            .add_observer(rebase_sway_rests);
+    }
+}
+
+/// Shifts the rest poses of root entities along with a rebased world origin. Children rest
+/// relative to their parents, which have already moved.
+fn rebase_sway_rests(
+    trigger: Trigger<OriginRebased>,
+    mut sways: Query<&mut FlowSwayState, Without<ChildOf>>,
+) {
+    for mut sway in &mut sways {
+        if let Some(rest) = &mut sway.rest {
+            rest.translation += trigger.offset;
+        }
+    }
+}
+
+fn integrate_flow_driven(
+    time: Res<Time>,
+    units: Res<VaneUnits>,
+    mut query: Query<(&FlowDriven, &VaneSamples, &mut BodyVelocity, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+    for (driven, samples, mut velocity, mut transform) in &mut query {
+        let flow = samples.mean();
+        let relative = flow.velocity() - velocity.0;
+        let drag = 0.5 * units.density(flow.density) * driven.cd * driven.drag_area
+            / driven.mass.max(f32::EPSILON);
+        // Clamped so that strong drag settles on the flow velocity instead of overshooting it.
+        let blend = (drag * relative.length() * dt).min(1.0);
+        velocity.0 += relative * blend;
+        transform.translation += velocity.0 * dt;
+    }
+}
+
+fn face_flow(
+    time: Res<Time>,
+    mut query: Query<(&FaceFlow, &VaneSamples, &GlobalTransform, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+    for (face_flow, samples, global_transform, mut transform) in &mut query {
+        let Ok(direction) = Dir3::new(samples.mean().velocity()) else {
+            continue;
+        };
+        // Rotation of the parent space, recovered from last frame's propagated transform.
+        let parent_rotation = global_transform.rotation() * transform.rotation.inverse();
+        let local_direction = parent_rotation.inverse() * direction;
+        let current_axis = transform.rotation * face_flow.axis;
+        let target = Quat::from_rotation_arc(current_axis.as_vec3(), local_direction.as_vec3())
+            * transform.rotation;
+
+        let t = 1.0 - (-face_flow.responsiveness * dt).exp();
+        transform.rotation = transform.rotation.slerp(target, t).normalize();
+    }
+}
+
+/// Rate at which [`FlowSwayState`] tracks the flow, in inverse seconds.
+const SWAY_SMOOTHING: f32 = 2.0;
+
+fn sway(
+    time: Res<Time>,
+    mut query: Query<(
+        &FlowSway,
+        &VaneSamples,
+        &GlobalTransform,
+        &mut FlowSwayState,
+        &mut Transform,
+    )>,
+) {
+    let dt = time.delta_secs();
+    let t = 1.0 - (-SWAY_SMOOTHING * dt).exp();
+    for (sway, samples, global_transform, mut state, mut transform) in &mut query {
+        let rest = *state.rest.get_or_insert(*transform);
+
+        let velocity = samples.mean().velocity();
+        let deviation = (velocity.length() - state.velocity.length()).abs();
+        state.velocity = state.velocity.lerp(velocity, t);
+        state.turbulence += (deviation - state.turbulence) * t;
+        state.phase = (state.phase + TAU * sway.frequency * dt) % TAU;
+
+        let full_speed = sway.full_speed.max(f32::EPSILON);
+        let strength = (state.velocity.length() / full_speed).min(1.0);
+        let gustiness = ((strength + state.turbulence / full_speed) * 0.5).min(1.0);
+        let amount = 0.5 * strength + 0.5 * gustiness * state.phase.sin();
+
+        let parent_rotation = global_transform.rotation() * transform.rotation.inverse();
+        let downwind = (parent_rotation.inverse() * state.velocity).normalize_or_zero();
+        let tilt_axis = Vec3::Y.cross(downwind).normalize_or_zero();
+
+        let tilt = if tilt_axis == Vec3::ZERO {
+            Quat::IDENTITY
+        } else {
+            Quat::from_axis_angle(tilt_axis, sway.max_angle * amount)
+        };
+        transform.rotation = tilt * rest.rotation;
+        transform.translation = rest.translation + downwind * sway.max_offset * amount;
+    }
+}
+
+fn sway_sprites(
+    time: Res<Time>,
+    mut query: Query<(
+        &WindSprite2d,
+        &VaneSamples,
+        &mut FlowSwayState,
+        &mut Transform,
+    )>,
+) {
+    let dt = time.delta_secs();
+    let t = 1.0 - (-SWAY_SMOOTHING * dt).exp();
+    for (sprite, samples, mut state, mut transform) in &mut query {
+        let rest = *state.rest.get_or_insert(*transform);
+
+        let velocity = samples.mean().velocity().with_z(0.0);
+        let deviation = (velocity.length() - state.velocity.length()).abs();
+        state.velocity = state.velocity.lerp(velocity, t);
+        state.turbulence += (deviation - state.turbulence) * t;
+        state.phase = (state.phase + TAU * sprite.frequency * dt) % TAU;
+
+        let full_speed = sprite.full_speed.max(f32::EPSILON);
+        let strength = (state.velocity.length() / full_speed).min(1.0);
+        let gustiness = ((strength + state.turbulence / full_speed) * 0.5).min(1.0);
+        let amount = 0.5 * strength + 0.5 * gustiness * state.phase.sin();
+
+        let downwind = state.velocity.normalize_or_zero();
+        // Leaning right is a clockwise rotation about Z, which is negative.
+        let lean = -downwind.x * sprite.max_angle * amount;
+        transform.rotation = Quat::from_rotation_z(lean) * rest.rotation;
+        transform.translation = rest.translation + downwind * sprite.max_offset * amount;
+    }
+}
+
+fn push_characters(
+    time: Res<Time>,
+    units: Res<VaneUnits>,
+    mut query: Query<(
+        &WindPush,
+        &VaneSamples,
+        Option<&BodyVelocity>,
+        &mut WindPushOutput,
+    )>,
+) {
+    let dt = time.delta_secs();
+    for (push, samples, body_velocity, mut output) in &mut query {
+        let flow = samples.mean();
+        let relative = flow.velocity() - body_velocity.map_or(Vec3::ZERO, |v| v.0);
+        let drag =
+            0.5 * units.density(flow.density) * push.drag_area * relative.length() * relative;
+        let mut acceleration = (drag / push.mass.max(f32::EPSILON)).clamp_length_max(push.max_push);
+
+        if push.grounded {
+            let horizontal = acceleration.with_y(0.0);
+            let resisted = push.ground_friction * units.acceleration(GRAVITY);
+            let remaining = (horizontal.length() - resisted).max(0.0);
+            acceleration =
+                horizontal.normalize_or_zero() * remaining + Vec3::Y * acceleration.y.max(0.0);
+        }
+
+        *output = WindPushOutput {
+            acceleration,
+            displacement: acceleration * dt * dt,
+        };
+    }
+}
+
+fn steer_with_flow(
+    mut query: Query<(
+        &FlowSteering,
+        &VaneSamples,
+        Option<&BodyVelocity>,
+        &mut FlowSteeringOutput,
+    )>,
+) {
+    for (steering, samples, body_velocity, mut output) in &mut query {
+        let relative = samples.mean().velocity() - body_velocity.map_or(Vec3::ZERO, |v| v.0);
+        output.acceleration = (relative * steering.weight).clamp_length_max(steering.max_accel);
+    }
+}
@@ -0,0 +1,321 @@
+use core::{fmt, time::Duration};
+use std::sync::Arc;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::{
+    entity_disabling::Disabled,
+    prelude::{
+        Commands, Component, Entity, EntityCommands, EntityWorldMut, IntoScheduleConfigs, Query,
+        ReflectComponent, Res,
+    },
+};
+use bevy_math::curve::Curve;
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+use bevy_time::Time;
+
+use crate::flow::FlowInfluence;
+
+/// Ramps a flow's influence up when it spawns and back down when it is released, so that
+/// spawned effects like gusts and spells fade in and out instead of popping.
+///
+/// The envelope's [`level`](Self::level) rises linearly from `0` to `1` over `attack`, holds for
+/// `sustain`, then falls linearly back to `0` over `release`. With no `sustain`, the level holds
+/// until [`release`](Self::release) is called or [`FlowEnvelopeCommandsExt::release_and_despawn`]
+/// is used. The level scales the flow's [`FlowInfluence`] when sampling, leaving the component
+/// itself untouched.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct FlowEnvelope {
+    pub attack: Duration,
+    /// How long to hold full influence before releasing, or `None` to hold until released.
+    pub sustain: Option<Duration>,
+    pub release: Duration,
+    /// Scales the envelope's level, for shaping a fade by hand or keyframing it in an animation
+    /// clip. Defaults to `1`.
+    pub gain: f32,
+    elapsed: Duration,
+    /// When the release started, and the level it started from.
+    released: Option<(Duration, f32)>,
+    despawn_when_finished: bool,
+    level: f32,
+}
+
+impl FlowEnvelope {
+    pub fn new(attack: Duration, sustain: Option<Duration>, release: Duration) -> Self {
+        Self {
+            attack,
+            sustain,
+            release,
+            gain: 1.0,
+            elapsed: Duration::ZERO,
+            released: None,
+            despawn_when_finished: false,
+            level: 0.0,
+        }
+    }
+
+    /// The current multiplier on the flow's influence, from `0` to `gain`.
+    pub fn level(&self) -> f32 {
+        self.level * self.gain
+    }
+
+    /// Time since the envelope started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Starts the release phase from the current level, if it hasn't started already.
+    pub fn release(&mut self) {
+        if self.released.is_none() {
+            self.released = Some((self.elapsed, self.level));
+        }
+    }
+
+    pub fn is_released(&self) -> bool {
+        self.released.is_some()
+    }
+
+    /// Starts the envelope over from the attack phase at level `0`, such as when reusing a pooled
+    /// flow.
+    pub fn restart(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.released = None;
+        self.despawn_when_finished = false;
+        self.level = 0.0;
+    }
+
+    /// Whether the release phase has completed.
+    pub fn is_finished(&self) -> bool {
+        self.released
+            .is_some_and(|(start, _)| self.elapsed >= start + self.release)
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        self.elapsed += delta;
+        if let Some(sustain) = self.sustain
+            && self.elapsed >= self.attack + sustain
+        {
+            self.release();
+        }
+
+        self.level = match self.released {
+            Some((start, from)) => {
+                let t = (self.elapsed - start).as_secs_f32() / self.release.as_secs_f32();
+                from * (1.0 - t).max(0.0)
+            }
+            None if self.elapsed < self.attack => {
+                self.elapsed.as_secs_f32() / self.attack.as_secs_f32()
+            }
+            None => 1.0,
+        };
+        if !self.level.is_finite() {
+            self.level = 0.0;
+        }
+    }
+}
+
+/// Ends a flow after the contained duration, for fire-and-forget effects.
+///
+/// The duration counts down every frame. If the flow has a [`FlowEnvelope`], its release starts
+/// early enough to finish exactly when the lifetime runs out. What happens then is decided by the
+/// flow's [`LifetimeEnd`].
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+#[require(LifetimeEnd)]
+pub struct FlowLifetime(pub Duration);
+
+/// What happens to a flow when its [`FlowLifetime`] runs out.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub enum LifetimeEnd {
+    #[default]
+    Despawn,
+    /// Inserts [`Disabled`] and removes the [`FlowLifetime`], so the flow can be pooled, and
+    /// [restarts](FlowEnvelope::restart) its [`FlowEnvelope`]. To reuse the flow, remove
+    /// [`Disabled`] and insert a new [`FlowLifetime`]: it fades in again from the attack.
+    Disable,
+}
+
+/// Sets a flow's [`FlowInfluence`] from a curve over the time since the component was added, for
+/// authoring storm build-ups and other long arcs without a bespoke system.
+///
+/// Time outside the curve's domain is clamped to it, so a curve over `0..60` holds its last value
+/// after a minute.
+#[derive(Component, Clone)]
+#[require(FlowInfluence)]
+pub struct InfluenceCurve {
+    curve: Arc<dyn Curve<f32> + Send + Sync>,
+    /// Multiplies the curve's value. Defaults to `1`.
+    pub scale: f32,
+    elapsed: Duration,
+}
+
+impl InfluenceCurve {
+    pub fn new(curve: impl Curve<f32> + Send + Sync + 'static) -> Self {
+        Self {
+            curve: Arc::new(curve),
+            scale: 1.0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Time since the curve started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The influence the curve gives at its current time.
+    pub fn value(&self) -> f32 {
+        self.curve.sample_clamped(self.elapsed.as_secs_f32()) * self.scale
+    }
+}
+
+impl fmt::Debug for InfluenceCurve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InfluenceCurve")
+            .field("domain", &self.curve.domain())
+            .field("scale", &self.scale)
+            .field("elapsed", &self.elapsed)
+            .finish_non_exhaustive()
+    }
+}
+
+pub trait FlowEnvelopeCommandsExt {
+    /// Releases the entity's [`FlowEnvelope`] and despawns the entity once the release finishes.
+    /// Entities without an envelope are despawned immediately.
+    fn release_and_despawn(&mut self) -> &mut Self;
+}
+
+impl FlowEnvelopeCommandsExt for EntityCommands<'_> {
+    fn release_and_despawn(&mut self) -> &mut Self {
+        self.queue(|mut entity: EntityWorldMut| {
+            if let Some(mut envelope) = entity.get_mut::<FlowEnvelope>() {
+                envelope.release();
+                envelope.despawn_when_finished = true;
+            } else {
+                entity.despawn();
+            }
+        })
+    }
+}
+
+pub struct EnvelopePlugin;
+
+impl Plugin for EnvelopePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FlowEnvelope>()
+            .register_type::<FlowLifetime>()
+            .register_type::<LifetimeEnd>()
+            .add_systems(
+                Update,
+                (update_lifetimes, update_envelopes, update_influence_curves).chain(),
+            );
+    }
+}
+
+fn update_envelopes(
+    time: Res<Time>,
+    mut envelopes: Query<(Entity, &mut FlowEnvelope)>,
+    mut commands: Commands,
+) {
+    for (entity, mut envelope) in &mut envelopes {
+        envelope.tick(time.delta());
+        if envelope.despawn_when_finished && envelope.is_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_influence_curves(
+    time: Res<Time>,
+    mut curves: Query<(&mut InfluenceCurve, &mut FlowInfluence)>,
+) {
+    for (mut curve, mut influence) in &mut curves {
+        curve.elapsed += time.delta();
+        influence.0 = curve.value();
+    }
+}
+
+fn update_lifetimes(
+    time: Res<Time>,
+    mut lifetimes: Query<(
+        Entity,
+        &mut FlowLifetime,
+        &LifetimeEnd,
+        Option<&mut FlowEnvelope>,
+    )>,
+    mut commands: Commands,
+) {
+    for (entity, mut lifetime, end, mut envelope) in &mut lifetimes {
+        lifetime.0 = lifetime.0.saturating_sub(time.delta());
+        if let Some(envelope) = &mut envelope
+            && lifetime.0 <= envelope.release
+        {
+            envelope.release();
+        }
+
+        if lifetime.0.is_zero() {
+            match end {
+                LifetimeEnd::Despawn => {
+                    commands.entity(entity).despawn();
+                }
+                LifetimeEnd::Disable => {
+                    if let Some(envelope) = &mut envelope {
+                        envelope.restart();
+                    }
+                    commands
+                        .entity(entity)
+                        .insert(Disabled)
+                        .remove::<FlowLifetime>();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::World;
+
+    use super::*;
+    use crate::test_utils::FlowWorldBuilder;
+
+    #[test]
+    fn pooled_flows_fade_in_again() {
+        let mut world = FlowWorldBuilder::default();
+        let second = Duration::from_secs(1);
+        let flow = world
+            .world_mut()
+            .spawn((
+                FlowEnvelope::new(second, None, second),
+                FlowLifetime(second * 3),
+                LifetimeEnd::Disable,
+            ))
+            .id();
+        world.step(200);
+        let level = |world: &World| world.get::<FlowEnvelope>(flow).unwrap().level();
+        assert!(world.world().entity(flow).contains::<Disabled>());
+        assert_eq!(level(world.world()), 0.0);
+        assert!(
+            !world
+                .world()
+                .get::<FlowEnvelope>(flow)
+                .unwrap()
+                .is_released()
+        );
+
+        world
+            .world_mut()
+            .entity_mut(flow)
+            .remove::<Disabled>()
+            .insert(FlowLifetime(second * 3));
+        world.step(30);
+        let level = level(world.world());
+        assert!((level - 0.5).abs() < 0.05, "the reused flow is at {level}");
+    }
+}
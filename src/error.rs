@@ -0,0 +1,69 @@
+use bevy_asset::{AssetServer, Assets, LoadState};
+use bevy_ecs::prelude::{Commands, Component, Entity, Has, Query, Res, Resource};
+use bevy_transform::components::GlobalTransform;
+use tracing::warn;
+
+use crate::{field::FlowField, flow::Flow};
+
+/// How the crate reacts to invalid data, such as flows with a degenerate transform or a field
+/// that failed to load.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VaneErrorPolicy {
+    /// Panic, to catch mistakes early in development.
+    Strict,
+    /// Log a warning and skip the offending entity or asset until it becomes valid again.
+    #[default]
+    Lenient,
+}
+
+impl VaneErrorPolicy {
+    /// Reports an error according to the policy.
+    pub fn report(self, message: core::fmt::Arguments) {
+        match self {
+            Self::Strict => panic!("{message}"),
+            Self::Lenient => warn!("{message}"),
+        }
+    }
+}
+
+/// Marks a [`Flow`] that was found invalid under [`VaneErrorPolicy::Lenient`]. It is skipped
+/// when sampling until it is valid again.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct InvalidFlow;
+
+pub(crate) fn validate_flows(
+    policy: Res<VaneErrorPolicy>,
+    flows: Query<(Entity, &Flow, &GlobalTransform, Has<InvalidFlow>)>,
+    fields: Res<Assets<FlowField>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for (entity, flow, transform, invalid) in &flows {
+        let matrix = transform.affine().matrix3;
+        let problem = if !transform.translation().is_finite() || !matrix.is_finite() {
+            Some("has a non-finite transform")
+        } else if matrix.determinant().abs() <= f32::EPSILON {
+            Some("has a degenerate transform")
+        } else if !fields.contains(&flow.field)
+            && matches!(
+                asset_server.get_load_state(&flow.field),
+                None | Some(LoadState::NotLoaded | LoadState::Failed(_))
+            )
+        {
+            Some("references a field that is missing or failed to load")
+        } else {
+            None
+        };
+
+        match (problem, invalid) {
+            (Some(problem), false) => {
+                policy.report(format_args!("flow {entity} {problem}"));
+                commands.entity(entity).insert(InvalidFlow);
+            }
+            (None, true) => {
+                commands.entity(entity).remove::<InvalidFlow>();
+            }
+            _ => {}
+        }
+    }
+}
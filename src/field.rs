@@ -0,0 +1,188 @@
+use bevy_asset::Asset;
+use bevy_math::{IVec3, UVec3, Vec3};
+use bevy_reflect::TypePath;
+use half::f16;
+
+use crate::flow::FlowVector;
+
+/// A baked 3D grid of [`FlowVector`]s.
+///
+/// Texel centers are spread evenly over the unit cube `[-0.5, 0.5]³`, which a
+/// [`Flow`](crate::flow::Flow) maps into the world with its transform.
+///
+/// Fields of still media, such as fog banks or bodies of water, can be created
+/// [density-only](Self::density_only) to store a half-precision density per texel instead of a
+/// whole vector. They sample with zero momentum.
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct FlowField {
+    size: UVec3,
+    data: FieldData,
+}
+
+#[derive(Clone, Debug)]
+enum FieldData {
+    Vectors(Vec<FlowVector>),
+    Density(Vec<f16>),
+}
+
+impl FlowField {
+    /// Creates an empty field with `size` texels along each axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any dimension of `size` is zero.
+    pub fn new(size: UVec3) -> Self {
+        assert!(
+            size.cmpgt(UVec3::ZERO).all(),
+            "flow field size must be non-zero"
+        );
+        Self {
+            size,
+            data: FieldData::Vectors(vec![FlowVector::ZERO; size.element_product() as usize]),
+        }
+    }
+
+    /// Creates a density-only field of zero density.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any dimension of `size` is zero.
+    pub fn density_only(size: UVec3) -> Self {
+        assert!(
+            size.cmpgt(UVec3::ZERO).all(),
+            "flow field size must be non-zero"
+        );
+        Self {
+            size,
+            data: FieldData::Density(vec![f16::ZERO; size.element_product() as usize]),
+        }
+    }
+
+    /// Creates a density-only field by evaluating `f` at the local position of every texel center.
+    pub fn density_from_fn(size: UVec3, mut f: impl FnMut(Vec3) -> f32) -> Self {
+        let mut field = Self::density_only(size);
+        field.fill(|position| FlowVector::new(Vec3::ZERO, f(position)));
+        field
+    }
+
+    pub fn is_density_only(&self) -> bool {
+        matches!(self.data, FieldData::Density(_))
+    }
+
+    /// Creates a field by evaluating `f` at the local position of every texel center.
+    pub fn from_fn(size: UVec3, f: impl FnMut(Vec3) -> FlowVector) -> Self {
+        let mut field = Self::new(size);
+        field.fill(f);
+        field
+    }
+
+    /// Overwrites every texel with `f` evaluated at its center, keeping the field's size.
+    pub fn fill(&mut self, mut f: impl FnMut(Vec3) -> FlowVector) {
+        for z in 0..self.size.z {
+            for y in 0..self.size.y {
+                for x in 0..self.size.x {
+                    let texel = UVec3::new(x, y, z);
+                    let value = f(self.texel_center(texel));
+                    self.set(texel, value);
+                }
+            }
+        }
+    }
+
+    pub fn size(&self) -> UVec3 {
+        self.size
+    }
+
+    /// The texels in x-major order, or `None` if the field is density-only.
+    pub fn data(&self) -> Option<&[FlowVector]> {
+        match &self.data {
+            FieldData::Vectors(data) => Some(data),
+            FieldData::Density(_) => None,
+        }
+    }
+
+    /// The texels in x-major order, widening a density-only field to full vectors first.
+    pub fn data_mut(&mut self) -> &mut [FlowVector] {
+        if let FieldData::Density(density) = &self.data {
+            let data = density
+                .iter()
+                .map(|density| FlowVector::new(Vec3::ZERO, density.to_f32()))
+                .collect();
+            self.data = FieldData::Vectors(data);
+        }
+        match &mut self.data {
+            FieldData::Vectors(data) => data,
+            FieldData::Density(_) => unreachable!(),
+        }
+    }
+
+    /// The position of the center of `texel` in the unit cube.
+    pub fn texel_center(&self, texel: UVec3) -> Vec3 {
+        (texel.as_vec3() + 0.5) / self.size.as_vec3() - 0.5
+    }
+
+    pub fn get(&self, texel: UVec3) -> FlowVector {
+        self.get_index(self.index(texel))
+    }
+
+    /// Sets a texel. A density-only field keeps only the density of `value`.
+    pub fn set(&mut self, texel: UVec3, value: FlowVector) {
+        let index = self.index(texel);
+        match &mut self.data {
+            FieldData::Vectors(data) => data[index] = value,
+            FieldData::Density(data) => data[index] = f16::from_f32(value.density),
+        }
+    }
+
+    /// The texel at `index` in the order of [`data`](Self::data).
+    pub(crate) fn get_index(&self, index: usize) -> FlowVector {
+        match &self.data {
+            FieldData::Vectors(data) => data[index],
+            FieldData::Density(data) => FlowVector::new(Vec3::ZERO, data[index].to_f32()),
+        }
+    }
+
+    /// Trilinearly samples the field at a position in the unit cube, clamping to the edge texels.
+    pub fn sample(&self, local: Vec3) -> FlowVector {
+        let max = (self.size - 1).as_vec3();
+        let coords = ((local + 0.5) * self.size.as_vec3() - 0.5).clamp(Vec3::ZERO, max);
+        let base = coords.floor();
+        let t = coords - base;
+        let base = base.as_uvec3();
+        let next = (base + 1).min(self.size - 1);
+
+        let at = |x: bool, y: bool, z: bool| {
+            self.get(UVec3::new(
+                if x { next.x } else { base.x },
+                if y { next.y } else { base.y },
+                if z { next.z } else { base.z },
+            ))
+        };
+        let lerp_x = |y, z| at(false, y, z).lerp(at(true, y, z), t.x);
+        let lerp_y = |z| lerp_x(false, z).lerp(lerp_x(true, z), t.y);
+        lerp_y(false).lerp(lerp_y(true), t.z)
+    }
+
+    /// Trilinearly samples the field as if it repeated endlessly, with one copy spanning each unit
+    /// cube.
+    pub fn sample_wrapped(&self, local: Vec3) -> FlowVector {
+        let size = self.size.as_ivec3();
+        let coords = (local + 0.5) * self.size.as_vec3() - 0.5;
+        let base = coords.floor();
+        let t = coords - base;
+        let base = base.as_ivec3();
+
+        let at = |x: bool, y: bool, z: bool| {
+            let texel = base + IVec3::new(x as i32, y as i32, z as i32);
+            self.get(texel.rem_euclid(size).as_uvec3())
+        };
+        let lerp_x = |y, z| at(false, y, z).lerp(at(true, y, z), t.x);
+        let lerp_y = |z| lerp_x(false, z).lerp(lerp_x(true, z), t.y);
+        lerp_y(false).lerp(lerp_y(true), t.z)
+    }
+
+    fn index(&self, texel: UVec3) -> usize {
+        debug_assert!(texel.cmplt(self.size).all(), "texel out of bounds");
+        (texel.x + self.size.x * (texel.y + self.size.y * texel.z)) as usize
+    }
+}
@@ -0,0 +1,1020 @@
+use core::{
+    f32::consts::TAU,
+    fmt,
+    ops::{Add, AddAssign, Mul, Sub},
+    time::Duration,
+};
+use std::{collections::HashMap, sync::Arc};
+
+use bevy_app::{App, FixedUpdate, Plugin, PostUpdate};
+use bevy_asset::{AssetApp, AssetServer, Handle};
+use bevy_ecs::{
+    prelude::{
+        Changed, Commands, Component, DetectChanges, Entity, IntoScheduleConfigs, Or, Query, Ref,
+        ReflectComponent, Res, Resource, SystemSet, Trigger,
+    },
+    schedule::{InternedScheduleLabel, ScheduleLabel},
+};
+use bevy_math::{Dir3, Vec3, Vec3Swizzles, curve::Curve};
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+use bevy_time::Time;
+use bevy_transform::{
+    TransformSystem,
+    components::{GlobalTransform, Transform},
+};
+
+use crate::{
+    bounds::{
+        FlowAabb, FlowGrid, WorldToLocal, update_flow_aabbs, update_flow_grid, update_region_grid,
+        update_world_to_local,
+    },
+    error::{VaneErrorPolicy, validate_flows},
+    field::FlowField,
+    occluder::WindOccluder,
+    region::OriginRebased,
+    volume::FlowVolume,
+};
+
+/// Density of dry air at sea level and 15 °C, in kg/m³.
+pub const AIR_DENSITY: f32 = 1.225;
+
+/// The mean wind speed in m/s at a Beaufort `level`, from the empirical relation
+/// `v = 0.836 · B^1.5`. The level is clamped to `0..=12`.
+///
+/// Convert the result with [`VaneUnits::speed`](crate::units::VaneUnits::speed) for other units.
+pub fn beaufort_speed(level: f32) -> f32 {
+    0.836 * level.clamp(0.0, 12.0).powf(1.5)
+}
+
+/// Below this density a [`FlowVector`] is considered empty and has no velocity.
+const DENSITY_EPSILON: f32 = 1e-6;
+
+/// The state of a moving medium at a point: its momentum density (kg/(m²·s)) and mass density
+/// (kg/m³).
+///
+/// Flows are composed by summing their vectors by default, so a vector carries the density of the
+/// medium along with its momentum.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FlowVector {
+    pub momentum: Vec3,
+    pub density: f32,
+}
+
+impl FlowVector {
+    pub const ZERO: Self = Self::new(Vec3::ZERO, 0.0);
+
+    pub const fn new(momentum: Vec3, density: f32) -> Self {
+        Self { momentum, density }
+    }
+
+    /// Creates a vector for a medium of the given density moving at `velocity`.
+    pub fn from_velocity(velocity: Vec3, density: f32) -> Self {
+        Self::new(velocity * density, density)
+    }
+
+    /// Air moving towards `direction` at the mean wind speed of a Beaufort `level`.
+    ///
+    /// Levels run from `0` (calm) to `12` (hurricane force) and may be fractional. A "force 7
+    /// from the west" is `FlowVector::beaufort(7.0, Dir3::X)` in a world where `+X` is east.
+    pub fn beaufort(level: f32, direction: Dir3) -> Self {
+        Self::from_velocity(direction * beaufort_speed(level), AIR_DENSITY)
+    }
+
+    /// The velocity of the medium, or zero if the density is (nearly) zero.
+    pub fn velocity(&self) -> Vec3 {
+        if self.density > DENSITY_EPSILON {
+            self.momentum / self.density
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    pub fn lerp(self, rhs: Self, t: f32) -> Self {
+        self + (rhs - self) * t
+    }
+}
+
+impl Add for FlowVector {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.momentum + rhs.momentum, self.density + rhs.density)
+    }
+}
+
+impl AddAssign for FlowVector {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for FlowVector {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.momentum - rhs.momentum, self.density - rhs.density)
+    }
+}
+
+impl Mul<f32> for FlowVector {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.momentum * rhs, self.density * rhs)
+    }
+}
+
+/// A volume of moving medium described by a [`FlowField`].
+///
+/// The field is stretched over the unit cube `[-0.5, 0.5]³` in the entity's local space, so the
+/// entity's [`Transform`] positions, orients, and sizes the volume.
+///
+/// Handles can't be saved in scenes, so a saved flow keeps its field through its
+/// [`FlowFieldPath`] instead.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+#[require(
+    Transform,
+    FlowInfluence,
+    FlowLayers,
+    InheritedVelocity,
+    FlowAabb,
+    WorldToLocal
+)]
+pub struct Flow {
+    #[reflect(skip_serializing)]
+    pub field: Handle<FlowField>,
+}
+
+impl Flow {
+    pub fn new(field: Handle<FlowField>) -> Self {
+        Self { field }
+    }
+}
+
+/// The asset path of a [`Flow`]'s field, which scenes save in place of the handle.
+///
+/// It is recorded for any field loaded from a path. When the path is set or changes, such as when
+/// a scene is loaded, the field is loaded from it.
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct FlowFieldPath(pub String);
+
+/// Extra fields layered on top of a [`Flow`]'s own field, such as detail turbulence over a base
+/// wind, so weighted combinations don't need to be baked into one field.
+///
+/// Each field is stretched over the same unit cube as the flow's field and added to it, scaled by
+/// its weight.
+#[derive(Component, Clone, Debug, Default)]
+pub struct FlowFieldStack(pub Vec<WeightedField>);
+
+impl FlowFieldStack {
+    pub fn with(mut self, field: Handle<FlowField>, weight: f32) -> Self {
+        self.0.push(WeightedField { field, weight });
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WeightedField {
+    pub field: Handle<FlowField>,
+    pub weight: f32,
+}
+
+/// Cross-fades a [`Flow`] from its old field to its new one when its handle is swapped, instead
+/// of snapping to the new field.
+///
+/// Both fields are sampled during the fade. Swapping again mid-fade restarts it from the field that
+/// was being faded to.
+#[derive(Component, Clone, Debug, Default)]
+pub struct FlowCrossfade {
+    pub duration: Duration,
+    current: Option<Handle<FlowField>>,
+    previous: Option<Handle<FlowField>>,
+    elapsed: Duration,
+}
+
+impl FlowCrossfade {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a fade is in progress.
+    pub fn is_fading(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// The field being faded out and its remaining weight, if a fade is in progress.
+    pub fn fading_out(&self) -> Option<(&Handle<FlowField>, f32)> {
+        let progress = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        let weight = if progress.is_nan() {
+            0.0
+        } else {
+            1.0 - progress.min(1.0)
+        };
+        self.previous.as_ref().map(|previous| (previous, weight))
+    }
+}
+
+/// Fades a [`Flow`] from one field to another over `duration`, then points the flow at `to` and
+/// removes itself.
+///
+/// Unlike [`FlowCrossfade`], the fade is started explicitly and both fields are given, which suits
+/// one-off changes such as a large ambient volume moving to a new weather state. The flow's own
+/// field is ignored while the fade runs.
+#[derive(Component, Clone, Debug)]
+pub struct FieldCrossfade {
+    pub from: Handle<FlowField>,
+    pub to: Handle<FlowField>,
+    pub duration: Duration,
+    elapsed: Duration,
+}
+
+impl FieldCrossfade {
+    pub fn new(from: Handle<FlowField>, to: Handle<FlowField>, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// How far the fade has come, from `0` (all `from`) to `1` (all `to`).
+    pub fn progress(&self) -> f32 {
+        let progress = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        if progress.is_nan() {
+            1.0
+        } else {
+            progress.min(1.0)
+        }
+    }
+}
+
+/// How a [`Flow`]'s field maps onto its volume.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub enum FlowExtent {
+    /// The field is stretched over the flow's unit cube, so scaling the flow stretches the field.
+    #[default]
+    Stretch,
+    /// Each texel spans `texel_size` world units whatever the flow's scale, and the field repeats
+    /// to fill the volume. Scaling the flow reveals more of the pattern instead of stretching it.
+    Tile { texel_size: f32 },
+}
+
+impl FlowExtent {
+    /// Maps `local` in a flow's unit cube to the position to sample `field` at, given the flow's
+    /// scale.
+    pub fn field_position(&self, field: &FlowField, local: Vec3, scale: Vec3) -> Vec3 {
+        match *self {
+            Self::Stretch => local,
+            Self::Tile { texel_size } => local * scale / (field.size().as_vec3() * texel_size),
+        }
+    }
+
+    /// Samples `field` at `local` in a flow's unit cube, given the flow's scale.
+    pub fn sample(&self, field: &FlowField, local: Vec3, scale: Vec3) -> FlowVector {
+        let position = self.field_position(field, local, scale);
+        match self {
+            Self::Stretch => field.sample(position),
+            Self::Tile { .. } => field.sample_wrapped(position),
+        }
+    }
+}
+
+/// Keeps a static field looking alive by scrolling and warping it over time.
+///
+/// Drifting fields are sampled as if they repeated endlessly, so they should tile seamlessly.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub struct FlowDrift {
+    /// How far the field scrolls per second, in copies of the field.
+    pub scroll: Vec3,
+    /// How far sample positions are pushed around by the warp, in copies of the field.
+    pub warp: f32,
+    /// Number of warp periods across the field.
+    pub warp_frequency: f32,
+    /// How fast the warp evolves, in radians per second.
+    pub warp_speed: f32,
+}
+
+impl FlowDrift {
+    /// Where to sample the field for `position` after `elapsed` seconds.
+    pub fn displace(&self, position: Vec3, elapsed: f64) -> Vec3 {
+        let scroll = (self.scroll.as_dvec3() * elapsed).fract().as_vec3();
+        let phase = (self.warp_speed as f64 * elapsed).rem_euclid(TAU as f64) as f32;
+        let p = position * self.warp_frequency * TAU + phase;
+        let warp = Vec3::new(p.y.sin(), p.z.sin(), p.x.sin()) * self.warp;
+        position - scroll + warp
+    }
+}
+
+/// Scales the contribution of a [`Flow`] to the composed flow.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component, Default)]
+pub struct FlowInfluence(pub f32);
+
+impl Default for FlowInfluence {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Scales a [`Flow`]'s contribution differently depending on the layers it is sampled on, on top
+/// of its [`FlowInfluence`], such as counting fully for effects but only weakly for physics.
+///
+/// Each entry pairs a layer mask with a multiplier. When sampled, the flow uses the largest
+/// multiplier among the entries sharing a layer with both the flow and the sampler, or `1` if
+/// none do.
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub struct FlowInfluencePerLayer(pub Vec<(FlowLayers, f32)>);
+
+impl FlowInfluencePerLayer {
+    pub fn with(mut self, layers: FlowLayers, multiplier: f32) -> Self {
+        self.0.push((layers, multiplier));
+        self
+    }
+
+    /// The multiplier for sampling on `shared`, the layers a flow shares with its sampler.
+    pub fn multiplier(&self, shared: FlowLayers) -> f32 {
+        self.0
+            .iter()
+            .filter(|(layers, _)| layers.intersects(&shared))
+            .map(|&(_, multiplier)| multiplier)
+            .reduce(f32::max)
+            .unwrap_or(1.0)
+    }
+}
+
+/// How a [`Flow`] combines with the flows composed before it.
+///
+/// Flows are composed in ascending `order`, starting from nothing. Flows without this component
+/// use [`BlendMode::Add`] with order `0`. The order of flows with equal keys is unspecified.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct FlowBlend {
+    pub mode: BlendMode,
+    pub order: i32,
+}
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Default)]
+pub enum BlendMode {
+    /// Adds the flow's weighted vector to the composition.
+    #[default]
+    Add,
+    /// Keeps the stronger of the composed and the flow's weighted momentum, and the larger
+    /// density.
+    Max,
+    /// Replaces the composition with the flow's vector, using its weight as opacity. This lets a
+    /// local effect like a tornado take over from ambient wind.
+    Override,
+    /// Multiplies the composition component-wise by the flow's vector, using its weight as
+    /// opacity. Useful with fields that store scale factors.
+    Multiply,
+    /// Adds the flow's weighted velocity to the composed velocity, keeping the composed density,
+    /// or the flow's own where nothing else flows. Suits gusts and other effects layered over
+    /// whatever wind is already there.
+    AddVelocity,
+}
+
+impl BlendMode {
+    /// Combines a flow's `sample` with the composition so far, given the flow's weight.
+    pub fn blend(self, composed: FlowVector, sample: FlowVector, weight: f32) -> FlowVector {
+        match self {
+            Self::Add => composed + sample * weight,
+            Self::Max => {
+                let sample = sample * weight;
+                let momentum =
+                    if sample.momentum.length_squared() > composed.momentum.length_squared() {
+                        sample.momentum
+                    } else {
+                        composed.momentum
+                    };
+                FlowVector::new(momentum, composed.density.max(sample.density))
+            }
+            Self::Override => composed.lerp(sample, weight),
+            Self::Multiply => {
+                let product = FlowVector::new(
+                    composed.momentum * sample.momentum,
+                    composed.density * sample.density,
+                );
+                composed.lerp(product, weight)
+            }
+            Self::AddVelocity => {
+                let density = if composed.density > DENSITY_EPSILON {
+                    composed.density
+                } else {
+                    sample.density
+                };
+                FlowVector::from_velocity(composed.velocity() + sample.velocity() * weight, density)
+            }
+        }
+    }
+}
+
+/// Feathers the edge of a [`Flow`] so it blends smoothly into the surrounding medium instead of
+/// cutting off at the boundary of its unit cube.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub struct FlowFalloff {
+    pub shape: FalloffShape,
+    /// Width of the blend band inside the shape's edge, in the flow's local units. The unit
+    /// cube has a half-extent of `0.5`, so `0.5` fades all the way from the edge to the center.
+    pub feather: f32,
+}
+
+/// The shape a [`FlowFalloff`] measures distance to, inscribed in the flow's unit cube.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Default)]
+pub enum FalloffShape {
+    #[default]
+    Box,
+    Sphere,
+    /// A cylinder along the local `Y` axis.
+    Cylinder,
+}
+
+impl FlowFalloff {
+    /// The weight of the flow at `local`, from `0` outside the shape to `1` deeper than
+    /// `feather` inside it.
+    pub fn weight(&self, local: Vec3) -> f32 {
+        let distance = match self.shape {
+            FalloffShape::Box => 0.5 - local.abs().max_element(),
+            FalloffShape::Sphere => 0.5 - local.length(),
+            FalloffShape::Cylinder => (0.5 - local.xz().length()).min(0.5 - local.y.abs()),
+        };
+        if distance <= 0.0 {
+            0.0
+        } else if distance >= self.feather {
+            1.0
+        } else {
+            let t = distance / self.feather;
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+/// Reshapes the speed a [`Flow`] contributes without rebaking its field, such as a dead zone
+/// below 1 m/s or a soft clamp above 30 m/s.
+///
+/// The curve maps the speed sampled from the flow, including its inherited velocity, to the
+/// speed it contributes. The direction and density are kept.
+#[derive(Component, Clone)]
+pub struct FlowRemap(pub Arc<dyn Curve<f32> + Send + Sync>);
+
+impl FlowRemap {
+    pub fn new(curve: impl Curve<f32> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(curve))
+    }
+
+    /// Applies the remap to `sample`.
+    pub fn apply(&self, sample: FlowVector) -> FlowVector {
+        let velocity = sample.velocity();
+        let speed = velocity.length();
+        if speed <= 0.0 {
+            return sample;
+        }
+        let remapped = velocity * (self.0.sample_clamped(speed) / speed);
+        FlowVector::from_velocity(remapped, sample.density)
+    }
+}
+
+impl fmt::Debug for FlowRemap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("FlowRemap").field(&self.0.domain()).finish()
+    }
+}
+
+/// The set of layers a [`Flow`] contributes to, or a vane samples from.
+///
+/// There are 64 layers, indexed `0..64`. Defaults to only layer `0`.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component, Default)]
+pub struct FlowLayers(pub u64);
+
+impl FlowLayers {
+    pub const COUNT: u8 = 64;
+
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    pub const fn all() -> Self {
+        Self(u64::MAX)
+    }
+
+    /// Creates a mask containing only `layer`.
+    pub const fn layer(layer: u8) -> Self {
+        Self::none().with(layer)
+    }
+
+    /// Creates a mask containing every layer in `layers`.
+    pub fn from_layers(layers: &[u8]) -> Self {
+        layers
+            .iter()
+            .fold(Self::none(), |mask, &layer| mask.with(layer))
+    }
+
+    pub const fn with(self, layer: u8) -> Self {
+        Self(self.0 | Self::bit(layer))
+    }
+
+    pub const fn without(self, layer: u8) -> Self {
+        Self(self.0 & !Self::bit(layer))
+    }
+
+    pub const fn contains(&self, layer: u8) -> bool {
+        self.0 & Self::bit(layer) != 0
+    }
+
+    pub const fn intersects(&self, other: &Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// The layers in both masks.
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Iterates over the indices of the layers in the mask.
+    pub fn iter(self) -> impl Iterator<Item = u8> {
+        (0..Self::COUNT).filter(move |&layer| self.contains(layer))
+    }
+
+    const fn bit(layer: u8) -> u64 {
+        assert!(layer < Self::COUNT, "flow layer out of range");
+        1 << layer
+    }
+}
+
+impl Default for FlowLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
+
+/// Names for flow layers, such as `"air"` or `"water"`, for more readable authoring.
+///
+/// The registry is optional and not added by any plugin; layers work the same without it.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct FlowLayerRegistry {
+    names: HashMap<String, u8>,
+}
+
+impl FlowLayerRegistry {
+    /// Names `layer`, replacing any layer previously registered under `name`.
+    pub fn insert(&mut self, name: impl Into<String>, layer: u8) -> &mut Self {
+        assert!(layer < FlowLayers::COUNT, "flow layer out of range");
+        self.names.insert(name.into(), layer);
+        self
+    }
+
+    /// Names the lowest layer that has no name yet and returns it, or the existing layer if
+    /// `name` is already registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if all layers are named.
+    pub fn register(&mut self, name: impl Into<String>) -> u8 {
+        let name = name.into();
+        if let Some(&layer) = self.names.get(&name) {
+            return layer;
+        }
+        let layer = (0..FlowLayers::COUNT)
+            .find(|layer| !self.names.values().any(|named| named == layer))
+            .expect("all flow layers are named");
+        self.names.insert(name, layer);
+        layer
+    }
+
+    pub fn get(&self, name: &str) -> Option<u8> {
+        self.names.get(name).copied()
+    }
+
+    /// The mask of the named layers, or `None` if any of them is not registered.
+    pub fn layers(&self, names: &[&str]) -> Option<FlowLayers> {
+        names.iter().try_fold(FlowLayers::none(), |mask, name| {
+            self.get(name).map(|layer| mask.with(layer))
+        })
+    }
+
+    pub fn name(&self, layer: u8) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|&(_, &named)| named == layer)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// Scales the influence of every flow on a layer, for turning whole layers up or down at once.
+///
+/// A flow on several sampled layers uses the largest of their multipliers. The resource is
+/// optional; without it every layer has a multiplier of `1`.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct FlowLayerInfluence([f32; FlowLayers::COUNT as usize]);
+
+impl FlowLayerInfluence {
+    pub fn get(&self, layer: u8) -> f32 {
+        self.0[layer as usize]
+    }
+
+    pub fn set(&mut self, layer: u8, multiplier: f32) -> &mut Self {
+        self.0[layer as usize] = multiplier;
+        self
+    }
+
+    /// The multiplier for a flow on `layers`, or `1` if the mask is empty.
+    pub fn multiplier(&self, layers: FlowLayers) -> f32 {
+        layers
+            .iter()
+            .map(|layer| self.get(layer))
+            .reduce(f32::max)
+            .unwrap_or(1.0)
+    }
+
+    /// Interpolates every layer's multiplier towards `other`.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self(core::array::from_fn(|layer| {
+            self.0[layer] + (other.0[layer] - self.0[layer]) * t
+        }))
+    }
+}
+
+impl Default for FlowLayerInfluence {
+    fn default() -> Self {
+        Self([1.0; FlowLayers::COUNT as usize])
+    }
+}
+
+/// The velocity a [`Flow`] inherits from its own motion.
+///
+/// It is added to every sample of the flow's field, so a flow attached to a moving object
+/// carries its medium along. Where it comes from is chosen by the flow's [`VelocitySource`].
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+#[require(VelocitySource)]
+pub struct InheritedVelocity {
+    pub linear: Vec3,
+    /// Angular velocity as a rotation axis scaled by radians per second.
+    pub angular: Vec3,
+    #[reflect(skip_serializing)]
+    previous_transform: Option<GlobalTransform>,
+    /// Time since `previous_transform` was taken, in seconds.
+    #[reflect(skip_serializing)]
+    pending_secs: f32,
+}
+
+impl InheritedVelocity {
+    /// The velocity of the point at `offset` from the flow's origin.
+    pub fn at(&self, offset: Vec3) -> Vec3 {
+        self.linear + self.angular.cross(offset)
+    }
+
+    /// Shifts the tracked transform along with a rebased world origin, so the shift doesn't
+    /// read as motion.
+    fn rebase(&mut self, offset: Vec3) {
+        if let Some(previous) = &mut self.previous_transform {
+            *previous = GlobalTransform::from_translation(offset) * *previous;
+        }
+    }
+}
+
+/// Where a [`Flow`]'s [`InheritedVelocity`] comes from.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub enum VelocitySource {
+    /// Finite differences of the flow's [`GlobalTransform`] between frames. Needs no setup, but
+    /// is noisy and a frame late for flows moved by physics.
+    #[default]
+    Transform,
+    /// The flow's [`FlowVelocity`], kept up to date by the user.
+    FlowVelocity,
+    /// The flow's rapier [`Velocity`](bevy_rapier3d::dynamics::Velocity).
+    #[cfg(feature = "rapier")]
+    Rapier,
+}
+
+/// A constant velocity a [`Flow`] advertises regardless of its [`VelocitySource`], such as the
+/// stream of air along a train that is really a static entity.
+///
+/// While present, it replaces the flow's [`InheritedVelocity`] every update.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub struct FlowVelocityOverride {
+    pub linear: Vec3,
+    pub angular: Vec3,
+}
+
+/// Tunes how [`VelocitySource::Transform`] turns motion into [`InheritedVelocity`].
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct FlowVelocitySettings {
+    /// Time constant of the exponential moving average applied to the measured velocity, in
+    /// seconds. `0` disables smoothing.
+    pub smoothing: f32,
+    /// Moves farther than this in one step, in meters, are treated as teleports: the velocity is
+    /// left unchanged and measuring starts over from the new position.
+    pub teleport_distance: f32,
+    /// Frames shorter than this, in seconds, are accumulated until they add up to it, so tiny
+    /// deltas don't amplify transform jitter.
+    pub min_delta_secs: f32,
+}
+
+impl Default for FlowVelocitySettings {
+    fn default() -> Self {
+        Self {
+            smoothing: 0.05,
+            teleport_distance: 10.0,
+            min_delta_secs: 1e-3,
+        }
+    }
+}
+
+/// A user-provided velocity for flows using [`VelocitySource::FlowVelocity`].
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub struct FlowVelocity {
+    pub linear: Vec3,
+    pub angular: Vec3,
+}
+
+/// Updates flow state, such as inherited velocities and bounds, before vanes sample it.
+///
+/// See [`VaneSystems`](crate::vane::VaneSystems) for how it fits into the frame.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FlowSystems;
+
+/// Registers the [`FlowField`] asset and keeps flow state up to date.
+///
+/// [`FlowSystems`] run in [`PostUpdate`] after transform propagation by default. Use
+/// [`FlowPlugin::fixed`] to step flow velocities and crossfades in [`FixedUpdate`] instead, so
+/// they step with a fixed-timestep physics engine. Flows' bounds and the [`FlowGrid`] are still
+/// updated in [`PostUpdate`] from the propagated transforms, so vanes sampling in [`PostUpdate`]
+/// see the flows where they are this frame, moving at the velocities of the last fixed step.
+pub struct FlowPlugin {
+    pub schedule: InternedScheduleLabel,
+}
+
+impl FlowPlugin {
+    pub fn fixed() -> Self {
+        Self {
+            schedule: FixedUpdate.intern(),
+        }
+    }
+}
+
+impl Default for FlowPlugin {
+    fn default() -> Self {
+        Self {
+            schedule: PostUpdate.intern(),
+        }
+    }
+}
+
+impl Plugin for FlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<FlowField>()
+            .init_resource::<FlowVelocitySettings>()
+            .init_resource::<VaneErrorPolicy>()
+            .init_resource::<FlowGrid>()
+            .register_type::<Flow>()
+            .register_type::<FlowFieldPath>()
+            .register_type::<FlowInfluence>()
+            .register_type::<FlowInfluencePerLayer>()
+            .register_type::<FlowLayers>()
+            .register_type::<InheritedVelocity>()
+            .register_type::<FlowExtent>()
+            .register_type::<FlowDrift>()
+            .register_type::<FlowBlend>()
+            .register_type::<FlowFalloff>()
+            .register_type::<FlowVolume>()
+            .register_type::<WindOccluder>()
+            .register_type::<VelocitySource>()
+            .register_type::<FlowVelocityOverride>()
+            .register_type::<FlowVelocity>()
+            .add_observer(rebase_inherited_velocities)
+            .configure_sets(
+                PostUpdate,
+                FlowSystems.after(TransformSystem::TransformPropagate),
+            );
+
+        let step_flows = (
+            (
+                update_flow_velocities,
+                copy_flow_velocities,
+                #[cfg(feature = "rapier")]
+                copy_rapier_velocities,
+            ),
+            apply_velocity_overrides,
+            sync_field_paths,
+            update_field_crossfades,
+            update_crossfades,
+        )
+            .chain()
+            .in_set(FlowSystems);
+        let place_flows = (
+            (update_world_to_local, update_flow_aabbs),
+            (update_flow_grid, update_region_grid, validate_flows),
+        )
+            .chain()
+            .in_set(FlowSystems);
+        if self.schedule == PostUpdate.intern() {
+            app.add_systems(PostUpdate, (step_flows, place_flows).chain());
+        } else {
+            app.add_systems(self.schedule, step_flows)
+                .add_systems(PostUpdate, place_flows);
+        }
+    }
+}
+
+fn rebase_inherited_velocities(
+    trigger: Trigger<OriginRebased>,
+    mut velocities: Query<&mut InheritedVelocity>,
+) {
+    for mut velocity in &mut velocities {
+        velocity.rebase(trigger.offset);
+    }
+}
+
+fn update_flow_velocities(
+    time: Res<Time>,
+    settings: Res<FlowVelocitySettings>,
+    mut flows: Query<(&GlobalTransform, &VelocitySource, &mut InheritedVelocity)>,
+) {
+    for (transform, source, mut velocity) in &mut flows {
+        if *source != VelocitySource::Transform {
+            continue;
+        }
+        velocity.pending_secs += time.delta_secs();
+        let Some(previous) = velocity.previous_transform else {
+            velocity.previous_transform = Some(*transform);
+            velocity.pending_secs = 0.0;
+            continue;
+        };
+        let delta_secs = velocity.pending_secs;
+        if delta_secs < settings.min_delta_secs || delta_secs <= 0.0 {
+            continue;
+        }
+        velocity.previous_transform = Some(*transform);
+        velocity.pending_secs = 0.0;
+
+        let displacement = transform.translation() - previous.translation();
+        if displacement.length() > settings.teleport_distance {
+            continue;
+        }
+        let linear = displacement / delta_secs;
+        let mut delta = transform.rotation() * previous.rotation().inverse();
+        if delta.w < 0.0 {
+            delta = -delta;
+        }
+        let (axis, angle) = delta.to_axis_angle();
+        let angular = axis * angle / delta_secs;
+
+        let alpha = if settings.smoothing > 0.0 {
+            1.0 - (-delta_secs / settings.smoothing).exp()
+        } else {
+            1.0
+        };
+        velocity.linear = velocity.linear.lerp(linear, alpha);
+        velocity.angular = velocity.angular.lerp(angular, alpha);
+    }
+}
+
+fn apply_velocity_overrides(mut flows: Query<(&FlowVelocityOverride, &mut InheritedVelocity)>) {
+    for (velocity_override, mut velocity) in &mut flows {
+        velocity.linear = velocity_override.linear;
+        velocity.angular = velocity_override.angular;
+    }
+}
+
+fn sync_field_paths(
+    asset_server: Res<AssetServer>,
+    mut flows: Query<
+        (Entity, &mut Flow, Option<Ref<FlowFieldPath>>),
+        Or<(Changed<Flow>, Changed<FlowFieldPath>)>,
+    >,
+    mut commands: Commands,
+) {
+    for (entity, mut flow, path) in &mut flows {
+        let field_path = flow.field.path().map(ToString::to_string);
+        match path {
+            Some(path) if path.is_changed() && field_path.as_ref() != Some(&path.0) => {
+                flow.field = asset_server.load(path.0.clone());
+            }
+            _ => {
+                if let Some(field_path) = field_path
+                    && path.is_none_or(|path| path.0 != field_path)
+                {
+                    commands.entity(entity).insert(FlowFieldPath(field_path));
+                }
+            }
+        }
+    }
+}
+
+fn update_field_crossfades(
+    time: Res<Time>,
+    mut flows: Query<(
+        Entity,
+        &mut Flow,
+        &mut FieldCrossfade,
+        Option<&mut FlowCrossfade>,
+    )>,
+    mut commands: Commands,
+) {
+    for (entity, mut flow, mut fade, crossfade) in &mut flows {
+        fade.elapsed += time.delta();
+        if fade.elapsed < fade.duration {
+            continue;
+        }
+        flow.field = fade.to.clone();
+        // The fade already happened, so don't let the swap start another one.
+        if let Some(mut crossfade) = crossfade {
+            crossfade.current = Some(fade.to.clone());
+            crossfade.previous = None;
+        }
+        commands.entity(entity).remove::<FieldCrossfade>();
+    }
+}
+
+fn update_crossfades(time: Res<Time>, mut flows: Query<(&Flow, &mut FlowCrossfade)>) {
+    for (flow, mut crossfade) in &mut flows {
+        if crossfade.current.as_ref() != Some(&flow.field) {
+            let previous = crossfade.current.replace(flow.field.clone());
+            if previous.is_some() {
+                crossfade.previous = previous;
+                crossfade.elapsed = Duration::ZERO;
+            }
+        } else if crossfade.previous.is_some() {
+            crossfade.elapsed += time.delta();
+            if crossfade.elapsed >= crossfade.duration {
+                crossfade.previous = None;
+            }
+        }
+    }
+}
+
+fn copy_flow_velocities(
+    mut flows: Query<(&FlowVelocity, &VelocitySource, &mut InheritedVelocity)>,
+) {
+    for (flow_velocity, source, mut velocity) in &mut flows {
+        if *source == VelocitySource::FlowVelocity {
+            velocity.linear = flow_velocity.linear;
+            velocity.angular = flow_velocity.angular;
+        }
+    }
+}
+
+#[cfg(feature = "rapier")]
+fn copy_rapier_velocities(
+    mut flows: Query<(
+        &bevy_rapier3d::dynamics::Velocity,
+        &VelocitySource,
+        &mut InheritedVelocity,
+    )>,
+) {
+    for (rapier_velocity, source, mut velocity) in &mut flows {
+        if *source == VelocitySource::Rapier {
+            velocity.linear = rapier_velocity.linvel;
+            velocity.angular = rapier_velocity.angvel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::{
+        measure::WindVelocity,
+        test_utils::{FlowWorldBuilder, measured},
+    };
+
+    #[test]
+    fn fixed_flows_are_placed_where_they_are_this_frame() {
+        let mut world = FlowWorldBuilder::fixed(Duration::from_secs_f32(1.0 / 60.0));
+        let flow = world.uniform_flow(Vec3::X * 5.0, Transform::from_scale(Vec3::splat(2.0)));
+        let vane = world.vane(Vec3::X * 10.0);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+        world.step(3);
+        assert_eq!(measured::<WindVelocity>(world.world(), vane), Vec3::ZERO);
+
+        world
+            .world_mut()
+            .get_mut::<Transform>(flow)
+            .unwrap()
+            .translation = Vec3::X * 10.0;
+        world.step(1);
+        let wind = measured::<WindVelocity>(world.world(), vane);
+        assert!(
+            wind.abs_diff_eq(Vec3::X * 5.0, 1e-4),
+            "the vane read {wind}"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use core::f32::consts::TAU;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{AssetId, Assets};
+use bevy_ecs::prelude::{
+    Changed, Command, Commands, Component, Entity, Event, EventReader, IntoScheduleConfigs, Query,
+    ReflectComponent, Res, ResMut, World,
+};
+use bevy_math::{UVec3, Vec3, Vec3Swizzles};
+use bevy_reflect::Reflect;
+use bevy_transform::components::Transform;
+
+use crate::{
+    error::VaneErrorPolicy,
+    field::FlowField,
+    flow::{AIR_DENSITY, Flow, FlowSystems, FlowVector},
+};
+
+/// A description of a field that can be evaluated at any point of a flow's unit cube.
+///
+/// Unlike a baked [`FlowField`], a recipe is small and reflectable, so it can be stored in scenes.
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub enum FlowRecipe {
+    /// The same velocity everywhere.
+    Uniform { velocity: Vec3, density: f32 },
+    /// Rotation about the local `Y` axis, with the tangential speed peaking at `speed` halfway to
+    /// the edge of the cube.
+    Vortex { speed: f32, density: f32 },
+    /// Outflow from the center, peaking at `speed` halfway to the edge of the cube.
+    Radial { speed: f32, density: f32 },
+    /// A base velocity perturbed by smooth, periodic turbulence.
+    Turbulence {
+        velocity: Vec3,
+        amplitude: f32,
+        /// Number of turbulence periods across the cube.
+        frequency: f32,
+        seed: u32,
+        density: f32,
+    },
+}
+
+impl Default for FlowRecipe {
+    fn default() -> Self {
+        Self::Uniform {
+            velocity: Vec3::ZERO,
+            density: AIR_DENSITY,
+        }
+    }
+}
+
+impl FlowRecipe {
+    /// Evaluates the recipe at `local` in the unit cube.
+    pub fn evaluate(&self, local: Vec3) -> FlowVector {
+        match *self {
+            Self::Uniform { velocity, density } => FlowVector::from_velocity(velocity, density),
+            Self::Vortex { speed, density } => {
+                let radial = local.xz();
+                let profile = (TAU * radial.length()).sin().max(0.0);
+                let tangent = Vec3::new(-radial.y, 0.0, radial.x).normalize_or_zero();
+                FlowVector::from_velocity(tangent * speed * profile, density)
+            }
+            Self::Radial { speed, density } => {
+                let profile = (TAU * local.length()).sin().max(0.0);
+                FlowVector::from_velocity(local.normalize_or_zero() * speed * profile, density)
+            }
+            Self::Turbulence {
+                velocity,
+                amplitude,
+                frequency,
+                seed,
+                density,
+            } => {
+                let phase = Vec3::new(1.0, 1.7, 2.3) * seed as f32;
+                let p = local * frequency * TAU + phase;
+                let offset = Vec3::new(
+                    p.y.sin() + (p.z * 0.7).cos(),
+                    p.z.sin() + (p.x * 0.7).cos(),
+                    p.x.sin() + (p.y * 0.7).cos(),
+                ) * 0.5;
+                FlowVector::from_velocity(velocity + offset * amplitude, density)
+            }
+        }
+    }
+
+    /// Bakes the recipe into a field of `size` texels.
+    pub fn bake(&self, size: UVec3) -> FlowField {
+        FlowField::from_fn(size, |local| self.evaluate(local))
+    }
+}
+
+/// Bakes a [`FlowRecipe`] into a new [`FlowField`] and points the entity's [`Flow`] at it,
+/// inserting one if needed.
+///
+/// The field is baked again whenever this component changes, so flows can be authored entirely
+/// in scenes.
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+#[require(Transform)]
+pub struct GeneratedFlow {
+    pub size: UVec3,
+    pub recipe: FlowRecipe,
+}
+
+impl Default for GeneratedFlow {
+    fn default() -> Self {
+        Self {
+            size: UVec3::splat(16),
+            recipe: FlowRecipe::default(),
+        }
+    }
+}
+
+/// Re-runs a [`FlowRecipe`] into an existing [`FlowField`] in place, keeping its size and every
+/// handle to it, so scripted weather can reshape fields without allocating new ones.
+///
+/// Works both as a [`Command`] and as an [`Event`]. Missing fields are reported according to the
+/// [`VaneErrorPolicy`].
+#[derive(Event, Clone, Debug)]
+pub struct RegenerateFlowField {
+    pub target: AssetId<FlowField>,
+    pub generator: FlowRecipe,
+}
+
+impl RegenerateFlowField {
+    fn regenerate(&self, fields: &mut Assets<FlowField>, policy: VaneErrorPolicy) {
+        match fields.get_mut(self.target) {
+            Some(field) => field.fill(|local| self.generator.evaluate(local)),
+            None => policy.report(format_args!(
+                "cannot regenerate missing flow field {}",
+                self.target
+            )),
+        }
+    }
+}
+
+impl Command for RegenerateFlowField {
+    fn apply(self, world: &mut World) {
+        let policy = world
+            .get_resource::<VaneErrorPolicy>()
+            .copied()
+            .unwrap_or_default();
+        self.regenerate(&mut world.resource_mut::<Assets<FlowField>>(), policy);
+    }
+}
+
+pub struct GeneratedFlowPlugin;
+
+impl Plugin for GeneratedFlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GeneratedFlow>()
+            .add_event::<RegenerateFlowField>()
+            .add_systems(
+                PostUpdate,
+                (bake_generated_flows, regenerate_flow_fields).before(FlowSystems),
+            );
+    }
+}
+
+fn bake_generated_flows(
+    generated: Query<(Entity, &GeneratedFlow), Changed<GeneratedFlow>>,
+    mut fields: ResMut<Assets<FlowField>>,
+    mut commands: Commands,
+) {
+    trace_span!("vane::bake_generated");
+    for (entity, generated) in &generated {
+        let field = fields.add(generated.recipe.bake(generated.size));
+        commands.entity(entity).insert(Flow::new(field));
+    }
+}
+
+fn regenerate_flow_fields(
+    mut events: EventReader<RegenerateFlowField>,
+    mut fields: ResMut<Assets<FlowField>>,
+    policy: Res<VaneErrorPolicy>,
+) {
+    trace_span!("vane::regenerate_fields");
+    for event in events.read() {
+        event.regenerate(&mut fields, *policy);
+    }
+}
@@ -0,0 +1,270 @@
+use core::time::Duration;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_asset::Assets;
+use bevy_ecs::prelude::{Commands, Component, Res, ResMut, Resource};
+use bevy_math::{Dir3, Quat, UVec3, Vec3};
+use bevy_time::Time;
+use bevy_transform::components::Transform;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    envelope::{FlowEnvelope, FlowLifetime, InfluenceCurve},
+    field::FlowField,
+    flow::{AIR_DENSITY, BlendMode, Flow, FlowBlend, FlowInfluence, FlowLayers, FlowVector},
+    units::VaneUnits,
+};
+
+/// Spawns random gusts of wind according to [`GustSettings`].
+///
+/// Each gust is a short-lived [`Gust`] flow filling the settings' `bounds`, which blows along the
+/// prevailing direction, fades in over `rise`, holds, then fades out over `fall` and despawns.
+/// Gusts add their speed to whatever wind is already blowing, and blow through still air on their
+/// own.
+/// The plugin isn't part of [`VanePlugins`](crate::VanePlugins); add it to opt in.
+pub struct GustPlugin;
+
+impl Plugin for GustPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GustSettings>()
+            .init_resource::<GustState>()
+            .init_resource::<VaneUnits>()
+            .add_systems(Update, spawn_gusts);
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct GustSettings {
+    pub enabled: bool,
+    /// Seed for the gust sequence. Changing it restarts the sequence.
+    pub seed: u64,
+    /// The prevailing direction gusts blow towards.
+    pub direction: Dir3,
+    /// Maximum deviation of a gust from `direction`, in radians.
+    pub direction_spread: f32,
+    /// The distribution of the speed gusts add, in meters per second, converted with the
+    /// [`VaneUnits`].
+    pub intensity: GustIntensity,
+    /// Bounds of the random time between the starts of consecutive gusts.
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+    pub rise: Duration,
+    pub hold: Duration,
+    pub fall: Duration,
+    /// The volume gusts fill, as the transform of their unit cube.
+    pub bounds: Transform,
+    pub layers: FlowLayers,
+    /// Shapes each gust's influence over its lifetime, scaled by its intensity. The rise and fall
+    /// still apply on top.
+    pub shape: Option<InfluenceCurve>,
+}
+
+impl Default for GustSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            seed: 0,
+            direction: Dir3::X,
+            direction_spread: 0.3,
+            intensity: GustIntensity::Exponential {
+                mean: 3.0,
+                max: 12.0,
+            },
+            min_interval: Duration::from_secs(2),
+            max_interval: Duration::from_secs(8),
+            rise: Duration::from_millis(800),
+            hold: Duration::from_millis(500),
+            fall: Duration::from_millis(1500),
+            bounds: Transform::from_scale(Vec3::splat(1000.0)),
+            layers: FlowLayers::default(),
+            shape: None,
+        }
+    }
+}
+
+/// How the speed of gusts is distributed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GustIntensity {
+    /// Every speed between `min` and `max` is equally likely.
+    Uniform { min: f32, max: f32 },
+    /// Mostly light gusts with the occasional strong one, capped at `max`.
+    Exponential { mean: f32, max: f32 },
+}
+
+impl GustIntensity {
+    fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Self::Uniform { min, max } => min + (max - min) * rng.random::<f32>(),
+            Self::Exponential { mean, max } => {
+                let u: f32 = rng.random();
+                (-mean * (1.0 - u).ln()).min(max)
+            }
+        }
+    }
+}
+
+/// A flow spawned by the [`GustPlugin`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Gust {
+    /// The speed the gust adds at its peak, in meters per second.
+    pub intensity: f32,
+    /// The direction the gust blows towards.
+    pub direction: Dir3,
+}
+
+#[derive(Resource)]
+struct GustState {
+    rng: ChaCha8Rng,
+    seed: u64,
+    until_next: Duration,
+}
+
+impl Default for GustState {
+    fn default() -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(0),
+            seed: 0,
+            until_next: Duration::ZERO,
+        }
+    }
+}
+
+fn spawn_gusts(
+    time: Res<Time>,
+    settings: Res<GustSettings>,
+    units: Res<VaneUnits>,
+    mut fields: ResMut<Assets<FlowField>>,
+    mut state: ResMut<GustState>,
+    mut commands: Commands,
+) {
+    if !settings.enabled {
+        return;
+    }
+    if state.seed != settings.seed {
+        *state = GustState {
+            rng: ChaCha8Rng::seed_from_u64(settings.seed),
+            seed: settings.seed,
+            until_next: Duration::ZERO,
+        };
+    }
+    state.until_next = state.until_next.saturating_sub(time.delta());
+    if !state.until_next.is_zero() {
+        return;
+    }
+
+    let state = &mut *state;
+    let interval = settings.max_interval.saturating_sub(settings.min_interval);
+    state.until_next = settings.min_interval + interval.mul_f32(state.rng.random());
+
+    let intensity = settings.intensity.sample(&mut state.rng);
+    let yaw = settings.direction_spread * state.rng.random_range(-1.0..=1.0);
+    let pitch = settings.direction_spread * state.rng.random_range(-1.0..=1.0) * 0.25;
+    let base = Quat::from_rotation_arc(Vec3::NEG_Z, settings.direction.as_vec3());
+    let rotation = base * Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch);
+    let direction = rotation * Dir3::NEG_Z;
+    // Field vectors are in world space, so each gust gets its own uniform field of air moving at
+    // 1 m/s, whose velocity is added scaled by the gust's influence.
+    let velocity = direction * units.speed(1.0);
+    let field = fields.add(FlowField::from_fn(UVec3::ONE, |_| {
+        FlowVector::from_velocity(velocity, AIR_DENSITY)
+    }));
+
+    let mut gust = commands.spawn((
+        Gust {
+            intensity,
+            direction,
+        },
+        Flow::new(field),
+        settings.bounds,
+        settings.layers,
+        FlowInfluence(intensity),
+        // After flows at the default order, so the gust adds to their wind.
+        FlowBlend {
+            mode: BlendMode::AddVelocity,
+            order: 1,
+        },
+        FlowEnvelope::new(settings.rise, Some(settings.hold), settings.fall),
+        FlowLifetime(settings.rise + settings.hold + settings.fall),
+    ));
+    if let Some(shape) = &settings.shape {
+        gust.insert(shape.clone().with_scale(intensity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::Entity;
+
+    use super::*;
+    use crate::{
+        measure::WindVelocity,
+        test_utils::{FlowWorldBuilder, measured},
+    };
+
+    fn steady_gusts() -> GustSettings {
+        GustSettings {
+            direction_spread: 0.0,
+            intensity: GustIntensity::Uniform { min: 3.0, max: 3.0 },
+            ..Default::default()
+        }
+    }
+
+    /// The highest wind speed along +X the vane reads over `frames`.
+    fn peak_speed(world: &mut FlowWorldBuilder, vane: Entity, frames: u32) -> f32 {
+        (0..frames).fold(0.0, |peak, _| {
+            world.step(1);
+            peak.max(measured::<WindVelocity>(world.world(), vane).x)
+        })
+    }
+
+    #[test]
+    fn gusts_blow_through_still_air() {
+        let mut world = FlowWorldBuilder::default();
+        world
+            .app_mut()
+            .add_plugins(GustPlugin)
+            .insert_resource(steady_gusts());
+        let vane = world.vane(Vec3::ZERO);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+
+        let peak = peak_speed(&mut world, vane, 180);
+        assert!((peak - 3.0).abs() < 0.1, "peak gust speed was {peak}");
+    }
+
+    #[test]
+    fn gusts_add_to_existing_wind() {
+        let mut world = FlowWorldBuilder::default();
+        world
+            .app_mut()
+            .add_plugins(GustPlugin)
+            .insert_resource(steady_gusts());
+        world.uniform_flow(Vec3::X * 5.0, Transform::from_scale(Vec3::splat(100.0)));
+        let vane = world.vane(Vec3::ZERO);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+
+        let peak = peak_speed(&mut world, vane, 180);
+        assert!(
+            (peak - 8.0).abs() < 0.1,
+            "peak speed with a gust was {peak}"
+        );
+    }
+
+    #[test]
+    fn gusts_blow_in_app_units() {
+        let mut world = FlowWorldBuilder::default();
+        world
+            .app_mut()
+            .add_plugins(GustPlugin)
+            .insert_resource(steady_gusts())
+            .insert_resource(VaneUnits::with_length(0.01));
+        let vane = world.vane(Vec3::ZERO);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+
+        let peak = peak_speed(&mut world, vane, 180);
+        assert!(
+            (peak - 300.0).abs() < 10.0,
+            "peak gust speed in centimeters was {peak}"
+        );
+    }
+}
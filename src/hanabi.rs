@@ -0,0 +1,280 @@
+//! Particle effects that move with the wind, through [bevy_hanabi], enabled by the `hanabi`
+//! feature.
+//!
+//! Give an effect a [`FlowVelocityModifier`] and its entity a [`FlowParticles`] pointing at a
+//! [`FlowCapture`]. Every frame, the capture is resampled into a small lattice of effect
+//! properties, which the modifier interpolates at each particle to pull its velocity toward the
+//! wind. Hanabi can't sample 3D textures from update shaders, so the lattice stands in for the
+//! full field: keep the capture small and centered on the view, and raise its resolution for
+//! effects that need finer detail than [`FLOW_PARTICLE_LATTICE`] texels.
+//!
+//! Particles are looked up by their world position, so effects must simulate in
+//! [`SimulationSpace::Global`](bevy_hanabi::SimulationSpace::Global), the default.
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::Assets;
+use bevy_ecs::prelude::{Component, Entity, IntoScheduleConfigs, Query, Res};
+use bevy_hanabi::{
+    Attribute, BoxedModifier, BuiltInOperator, EffectProperties, EvalContext, ExprError,
+    ExprHandle, Modifier, ModifierContext, Module, ShaderWriter, Value,
+    graph::expr::PropertyHandle,
+};
+use bevy_math::{UVec3, Vec3, Vec4};
+use bevy_reflect::Reflect;
+use bevy_transform::components::GlobalTransform;
+use serde::{Deserialize, Serialize};
+
+use crate::{capture::FlowCapture, field::FlowField, vane::VaneSystems};
+
+/// The number of texels along each axis of the lattice a [`FlowVelocityModifier`] interpolates.
+pub const FLOW_PARTICLE_LATTICE: u32 = 4;
+
+const LATTICE_LEN: usize =
+    (FLOW_PARTICLE_LATTICE * FLOW_PARTICLE_LATTICE * FLOW_PARTICLE_LATTICE) as usize;
+
+/// The rows of the transform from world space into the capture's unit cube.
+const TO_CAPTURE_PROPERTIES: [&str; 3] = [
+    "vane_flow_to_capture_x",
+    "vane_flow_to_capture_y",
+    "vane_flow_to_capture_z",
+];
+
+fn lattice_property(index: usize) -> String {
+    format!("vane_flow_{index}")
+}
+
+/// Feeds the wind baked by the [`FlowCapture`] on `capture` to this entity's particle effect,
+/// for its [`FlowVelocityModifier`].
+///
+/// Until the capture has baked, particles see still air.
+#[derive(Component, Clone, Debug)]
+#[require(EffectProperties)]
+pub struct FlowParticles {
+    pub capture: Entity,
+}
+
+impl FlowParticles {
+    pub fn new(capture: Entity) -> Self {
+        Self { capture }
+    }
+}
+
+/// An update modifier that pulls each particle's velocity toward the wind at its position, read
+/// from the [`FlowCapture`] bound with [`FlowParticles`].
+///
+/// `drag` is the rate, per second, at which particles match the wind: light particles like
+/// snow or dust want a high drag, heavy ones like leaves a low one. The modifier declares its
+/// properties when created, so create it with the module of the effect it goes in.
+#[derive(Clone, PartialEq, Debug, Reflect, Serialize, Deserialize)]
+pub struct FlowVelocityModifier {
+    pub drag: ExprHandle,
+    to_capture: [PropertyHandle; 3],
+    lattice: Vec<PropertyHandle>,
+}
+
+impl FlowVelocityModifier {
+    pub fn new(module: &mut Module, drag: ExprHandle) -> Self {
+        let to_capture =
+            TO_CAPTURE_PROPERTIES.map(|name| module.add_property(name, Vec4::ZERO.into()));
+        let lattice = (0..LATTICE_LEN)
+            .map(|index| module.add_property(lattice_property(index), Vec3::ZERO.into()))
+            .collect();
+        Self {
+            drag,
+            to_capture,
+            lattice,
+        }
+    }
+
+    /// Creates the modifier with a constant drag.
+    pub fn constant(module: &mut Module, drag: f32) -> Self {
+        let drag = module.lit(drag);
+        Self::new(module, drag)
+    }
+}
+
+#[typetag::serde]
+impl Modifier for FlowVelocityModifier {
+    fn context(&self) -> ModifierContext {
+        ModifierContext::Update
+    }
+
+    fn attributes(&self) -> &[Attribute] {
+        &[Attribute::POSITION, Attribute::VELOCITY]
+    }
+
+    fn boxed_clone(&self) -> BoxedModifier {
+        Box::new(self.clone())
+    }
+
+    fn apply(&self, module: &mut Module, context: &mut ShaderWriter) -> Result<(), ExprError> {
+        let m = module;
+        let position = m.attr(Attribute::POSITION);
+        let position = context.eval(m, position)?;
+        let velocity = m.attr(Attribute::VELOCITY);
+        let velocity = context.eval(m, velocity)?;
+        let dt = m.builtin(BuiltInOperator::DeltaTime);
+        let dt = context.eval(m, dt)?;
+        let drag = context.eval(m, self.drag)?;
+        let [x, y, z] = self.to_capture.map(|row| {
+            let row = m.prop(row);
+            context.eval(m, row)
+        });
+        let (x, y, z) = (x?, y?, z?);
+        let lattice = self
+            .lattice
+            .iter()
+            .map(|texel| {
+                let texel = m.prop(*texel);
+                context.eval(m, texel)
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", ");
+
+        // The same trilinear lookup as `FlowField::sample`, clamping to the edge texels.
+        let n = FLOW_PARTICLE_LATTICE;
+        context.main_code += &format!(
+            r"{{
+    var vane_lattice = array<vec3<f32>, {LATTICE_LEN}>({lattice});
+    let vane_point = vec4<f32>({position}, 1.0);
+    let vane_local = vec3<f32>(dot({x}, vane_point), dot({y}, vane_point), dot({z}, vane_point));
+    let vane_coords = clamp((vane_local + 0.5) * {n}.0 - 0.5, vec3<f32>(0.0), vec3<f32>({n}.0 - 1.0));
+    let vane_t = fract(vane_coords);
+    let vane_base = vec3<u32>(floor(vane_coords));
+    let vane_next = min(vane_base + 1u, vec3<u32>({n}u - 1u));
+    let vane_x0 = mix(
+        vane_lattice[vane_base.x + {n}u * (vane_base.y + {n}u * vane_base.z)],
+        vane_lattice[vane_next.x + {n}u * (vane_base.y + {n}u * vane_base.z)],
+        vane_t.x,
+    );
+    let vane_x1 = mix(
+        vane_lattice[vane_base.x + {n}u * (vane_next.y + {n}u * vane_base.z)],
+        vane_lattice[vane_next.x + {n}u * (vane_next.y + {n}u * vane_base.z)],
+        vane_t.x,
+    );
+    let vane_x2 = mix(
+        vane_lattice[vane_base.x + {n}u * (vane_base.y + {n}u * vane_next.z)],
+        vane_lattice[vane_next.x + {n}u * (vane_base.y + {n}u * vane_next.z)],
+        vane_t.x,
+    );
+    let vane_x3 = mix(
+        vane_lattice[vane_base.x + {n}u * (vane_next.y + {n}u * vane_next.z)],
+        vane_lattice[vane_next.x + {n}u * (vane_next.y + {n}u * vane_next.z)],
+        vane_t.x,
+    );
+    let vane_wind = mix(mix(vane_x0, vane_x1, vane_t.y), mix(vane_x2, vane_x3, vane_t.y), vane_t.z);
+    {velocity} = mix({velocity}, vane_wind, clamp({drag} * {dt}, 0.0, 1.0));
+}}
+"
+        );
+        Ok(())
+    }
+}
+
+pub struct FlowParticlesPlugin;
+
+impl Plugin for FlowParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, bind_flow_particles.after(VaneSystems::Sample));
+    }
+}
+
+/// Resamples each bound capture into the lattice properties of its effects.
+fn bind_flow_particles(
+    mut effects: Query<(&FlowParticles, &mut EffectProperties)>,
+    captures: Query<(&FlowCapture, &GlobalTransform)>,
+    fields: Res<Assets<FlowField>>,
+) {
+    trace_span!("vane::bind_flow_particles");
+    for (particles, mut properties) in &mut effects {
+        let Ok((capture, transform)) = captures.get(particles.capture) else {
+            continue;
+        };
+        let Some(field) = capture.field().and_then(|field| fields.get(field)) else {
+            continue;
+        };
+
+        let to_capture = transform.compute_matrix().inverse().transpose();
+        for (name, row) in TO_CAPTURE_PROPERTIES
+            .iter()
+            .zip(to_capture.to_cols_array_2d())
+        {
+            properties.set(name, Value::from(Vec4::from_array(row)));
+        }
+        let n = FLOW_PARTICLE_LATTICE;
+        for index in 0..LATTICE_LEN {
+            let i = index as u32;
+            let texel = UVec3::new(i % n, i / n % n, i / (n * n));
+            let local = (texel.as_vec3() + 0.5) / n as f32 - 0.5;
+            let wind = field.sample(local).velocity();
+            properties.set(&lattice_property(index), wind.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_hanabi::{ParticleLayout, PropertyLayout};
+    use bevy_math::Quat;
+    use bevy_transform::components::Transform;
+
+    use super::*;
+    use crate::test_utils::FlowWorldBuilder;
+
+    fn lattice_value(properties: &EffectProperties, index: usize) -> Vec3 {
+        properties
+            .get_stored(&lattice_property(index))
+            .unwrap()
+            .as_vector()
+            .as_vec3()
+    }
+
+    #[test]
+    fn effects_read_the_captured_wind() {
+        let mut world = FlowWorldBuilder::default();
+        world.app_mut().add_plugins(FlowParticlesPlugin);
+        world.uniform_flow(Vec3::X * 4.0, Transform::from_scale(Vec3::splat(100.0)));
+        let transform = Transform::from_translation(Vec3::new(1.0, 2.0, 3.0))
+            .with_rotation(Quat::from_rotation_y(1.0))
+            .with_scale(Vec3::splat(10.0));
+        let capture = world
+            .world_mut()
+            .spawn((FlowCapture::new(UVec3::splat(8)), transform))
+            .id();
+        let effect = world.world_mut().spawn(FlowParticles::new(capture)).id();
+        world.step(2);
+
+        let properties = world.world().get::<EffectProperties>(effect).unwrap();
+        for index in 0..LATTICE_LEN {
+            let wind = lattice_value(properties, index);
+            assert!(
+                wind.abs_diff_eq(Vec3::X * 4.0, 1e-3),
+                "texel {index} read {wind}"
+            );
+        }
+
+        // The rows map the capture's center to the origin of its unit cube.
+        let center = transform.translation.extend(1.0);
+        for name in TO_CAPTURE_PROPERTIES {
+            let row = properties.get_stored(name).unwrap().as_vector().as_vec4();
+            assert!(row.dot(center).abs() < 1e-4, "{name} is {row}");
+        }
+    }
+
+    #[test]
+    fn modifier_declares_its_properties() {
+        let mut module = Module::default();
+        let modifier = FlowVelocityModifier::constant(&mut module, 2.0);
+        assert_eq!(module.properties().len(), LATTICE_LEN + 3);
+
+        let property_layout = PropertyLayout::new(module.properties());
+        let particle_layout = ParticleLayout::new()
+            .append(Attribute::POSITION)
+            .append(Attribute::VELOCITY)
+            .build();
+        let mut context =
+            ShaderWriter::new(ModifierContext::Update, &property_layout, &particle_layout);
+        modifier.apply(&mut module, &mut context).unwrap();
+        assert!(context.main_code.contains("vane_wind"));
+    }
+}
@@ -0,0 +1,196 @@
+use core::{f32::consts::PI, time::Duration};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::prelude::{
+    Commands, Component, Entity, EntityCommands, FromWorld, OnAdd, Query, Res, Resource, Trigger,
+    World,
+};
+use bevy_math::{UVec3, Vec3};
+use bevy_time::Time;
+use bevy_transform::components::Transform;
+
+use crate::{
+    field::FlowField,
+    flow::{AIR_DENSITY, BlendMode, Flow, FlowBlend, FlowInfluence, FlowVector},
+};
+
+/// Resolution of the shared radial field used by every [`FlowImpulse`].
+const IMPULSE_FIELD_SIZE: u32 = 16;
+
+/// A short-lived radial burst of flow, for explosions, landing rockets, and door slams.
+///
+/// The burst blows outward from the center of the entity's unit sphere (so a [`Transform`] scale
+/// of `2 * radius`), peaking halfway to the edge. Its influence decays quadratically to zero over
+/// `duration`, after which the entity despawns.
+///
+/// The burst adds its velocity to whatever wind is already there, like a gust, and blows through
+/// still air on its own. `strength` is the peak speed it adds.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(Transform, FlowInfluence)]
+pub struct FlowImpulse {
+    /// Peak added speed in meters per second.
+    pub strength: f32,
+    pub duration: Duration,
+    pub elapsed: Duration,
+}
+
+impl FlowImpulse {
+    pub fn new(strength: f32, duration: Duration) -> Self {
+        Self {
+            strength,
+            duration,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+pub trait FlowImpulseCommandsExt {
+    /// Spawns a [`FlowImpulse`] of the given `radius` centered at `center`.
+    fn spawn_flow_impulse(
+        &mut self,
+        center: Vec3,
+        strength: f32,
+        radius: f32,
+        duration: Duration,
+    ) -> EntityCommands<'_>;
+}
+
+impl FlowImpulseCommandsExt for Commands<'_, '_> {
+    fn spawn_flow_impulse(
+        &mut self,
+        center: Vec3,
+        strength: f32,
+        radius: f32,
+        duration: Duration,
+    ) -> EntityCommands<'_> {
+        self.spawn((
+            Transform::from_translation(center).with_scale(Vec3::splat(2.0 * radius)),
+            FlowImpulse::new(strength, duration),
+        ))
+    }
+}
+
+/// The radial field shared by all impulses.
+#[derive(Resource)]
+struct FlowImpulseField(Handle<FlowField>);
+
+impl FromWorld for FlowImpulseField {
+    fn from_world(world: &mut World) -> Self {
+        let field = FlowField::from_fn(UVec3::splat(IMPULSE_FIELD_SIZE), |local| {
+            let radius = local.length() * 2.0;
+            // Still air past the edge, so texels interpolated across it keep the density.
+            let speed = if radius < 1.0 {
+                (PI * radius).sin()
+            } else {
+                0.0
+            };
+            FlowVector::from_velocity(local.normalize_or_zero() * speed, AIR_DENSITY)
+        });
+        Self(world.resource_mut::<Assets<FlowField>>().add(field))
+    }
+}
+
+pub struct FlowImpulsePlugin;
+
+impl Plugin for FlowImpulsePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlowImpulseField>()
+            .add_observer(attach_impulse_field)
+            .add_systems(Update, update_impulses);
+    }
+}
+
+fn attach_impulse_field(
+    trigger: Trigger<OnAdd, FlowImpulse>,
+    field: Res<FlowImpulseField>,
+    mut commands: Commands,
+) {
+    commands.entity(trigger.target()).insert((
+        Flow::new(field.0.clone()),
+        // After flows at the default order, so the burst adds to their wind.
+        FlowBlend {
+            mode: BlendMode::AddVelocity,
+            order: 1,
+        },
+    ));
+}
+
+fn update_impulses(
+    time: Res<Time>,
+    mut impulses: Query<(Entity, &mut FlowImpulse, &mut FlowInfluence)>,
+    mut commands: Commands,
+) {
+    for (entity, mut impulse, mut influence) in &mut impulses {
+        impulse.elapsed += time.delta();
+        if impulse.elapsed >= impulse.duration {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let remaining = 1.0 - impulse.elapsed.as_secs_f32() / impulse.duration.as_secs_f32();
+        influence.0 = impulse.strength * remaining * remaining;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        measure::WindVelocity,
+        test_utils::{FlowWorldBuilder, measured},
+    };
+
+    #[test]
+    fn impulses_blow_outward_then_vanish() {
+        let mut world = FlowWorldBuilder::default();
+        world.uniform_flow(Vec3::ZERO, Transform::from_scale(Vec3::splat(100.0)));
+        let impulse = world
+            .world_mut()
+            .spawn((
+                Transform::from_scale(Vec3::splat(4.0)),
+                FlowImpulse::new(10.0, Duration::from_secs(1)),
+            ))
+            .id();
+        // Halfway to the edge, where the burst peaks.
+        let vanes = [Vec3::X, Vec3::NEG_Y].map(|position| {
+            let vane = world.vane(position);
+            world.world_mut().entity_mut(vane).insert(WindVelocity);
+            (vane, position)
+        });
+        world.step(1);
+
+        for (vane, direction) in vanes {
+            let wind = measured::<WindVelocity>(world.world(), vane);
+            let outward = wind.dot(direction);
+            assert!(outward > 9.0 && outward <= 10.0, "the burst blew {wind}");
+            assert!(wind.reject_from(direction).length() < 1e-3, "{wind}");
+        }
+
+        world.step(30);
+        let (vane, direction) = vanes[0];
+        let outward = measured::<WindVelocity>(world.world(), vane).dot(direction);
+        assert!(
+            outward > 2.0 && outward < 3.0,
+            "the burst decayed to {outward}"
+        );
+
+        world.step(40);
+        assert!(world.world().get_entity(impulse).is_err());
+        assert_eq!(measured::<WindVelocity>(world.world(), vane), Vec3::ZERO);
+    }
+
+    #[test]
+    fn impulses_blow_through_still_air() {
+        let mut world = FlowWorldBuilder::default();
+        world.world_mut().spawn((
+            Transform::from_scale(Vec3::splat(4.0)),
+            FlowImpulse::new(10.0, Duration::from_secs(1)),
+        ));
+        let vane = world.vane(Vec3::Z);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+        world.step(1);
+
+        let wind = measured::<WindVelocity>(world.world(), vane);
+        assert!(wind.z > 9.0 && wind.z <= 10.0, "the burst blew {wind}");
+    }
+}
@@ -0,0 +1,392 @@
+//! An egui panel for inspecting and tuning the wind while the game runs, and floating readouts
+//! of measured values, behind the `egui` feature.
+
+use core::{any, fmt, marker::PhantomData};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::{
+    entity::EntityHashMap,
+    name::Name,
+    prelude::{Component, Entity, Mut, ParamSet, Query, Res, ResMut, Resource, With},
+    query::QueryData,
+    relationship::RelationshipTarget,
+    system::SystemParam,
+};
+use bevy_egui::{
+    EguiContextSettings, EguiContexts, EguiPrimaryContextPass, PrimaryEguiContext, egui,
+};
+use bevy_math::{Vec2, Vec3};
+use bevy_render::camera::Camera;
+use bevy_transform::components::GlobalTransform;
+
+use crate::{
+    activity::{Active, ActiveEntities, ActiveRegion, ActivityData, is_inactive},
+    flow::{Flow, FlowInfluence, FlowLayerRegistry, FlowLayers},
+    measure::{DynamicPressure, Measure, Measured, Vorticity, WindSpeed, WindVelocity},
+    region::{Contains, InRegion, Region},
+    sampler::FlowSampler,
+    vane::{Vane, VaneSamples, VaneUpdates},
+};
+
+/// Whether the [`WindInspectorPlugin`]'s window is shown. Closing the window clears `open`.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct WindInspector {
+    pub open: bool,
+}
+
+impl Default for WindInspector {
+    fn default() -> Self {
+        Self { open: true }
+    }
+}
+
+/// Shows a window listing regions and their flows, active regions with the number of entities
+/// inside them, the vanes currently sampling along with their built-in measures, and sampling
+/// counts.
+///
+/// Each flow's influence and layers can be edited in place. Layers `0..8` are offered, along with
+/// any named in the [`FlowLayerRegistry`]. Needs bevy_egui's `EguiPlugin`.
+pub struct WindInspectorPlugin;
+
+impl Plugin for WindInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WindInspector>()
+            .add_systems(EguiPrimaryContextPass, show_inspector);
+    }
+}
+
+/// Shows the latest [`Measured<M>`] value of a vane as a label floating above it, once a
+/// [`DebugMeasureTextPlugin<M>`] is added.
+///
+/// Labels are placed through the camera egui's primary context renders to, and hidden while the
+/// vane is behind it.
+#[derive(Component)]
+pub struct DebugMeasureText<M> {
+    /// The label's text before the value, or the measure's type name if `None`.
+    pub label: Option<String>,
+    /// Where the label floats relative to the vane, in world space.
+    pub offset: Vec3,
+    marker: PhantomData<M>,
+}
+
+impl<M> DebugMeasureText<M> {
+    pub fn new() -> Self {
+        Self {
+            label: None,
+            offset: Vec3::Y * 0.5,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+impl<M> Default for DebugMeasureText<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Clone for DebugMeasureText<M> {
+    fn clone(&self) -> Self {
+        Self {
+            label: self.label.clone(),
+            offset: self.offset,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M> fmt::Debug for DebugMeasureText<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DebugMeasureText")
+            .field("label", &self.label)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Shows [`DebugMeasureText<M>`]s. Needs bevy_egui's `EguiPlugin`.
+pub struct DebugMeasureTextPlugin<M>(PhantomData<M>);
+
+impl<M> Default for DebugMeasureTextPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Measure<Output: fmt::Debug>> Plugin for DebugMeasureTextPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(EguiPrimaryContextPass, show_measure_texts::<M>);
+    }
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct InspectedFlow {
+    entity: Entity,
+    name: Option<&'static Name>,
+    flow: &'static Flow,
+    transform: &'static GlobalTransform,
+    influence: &'static mut FlowInfluence,
+    layers: &'static mut FlowLayers,
+    region: Option<&'static InRegion>,
+    activity: ActivityData,
+}
+
+#[derive(QueryData)]
+struct InspectedVane {
+    entity: Entity,
+    name: Option<&'static Name>,
+    samples: &'static VaneSamples,
+    speed: Option<&'static Measured<WindSpeed>>,
+    velocity: Option<&'static Measured<WindVelocity>>,
+    pressure: Option<&'static Measured<DynamicPressure>>,
+    vorticity: Option<&'static Measured<Vorticity>>,
+}
+
+#[derive(SystemParam)]
+struct InspectedRegions<'w, 's> {
+    regions:
+        Query<'w, 's, (Entity, Option<&'static Name>, Option<&'static Contains>), With<Region>>,
+    active_regions: Query<'w, 's, (Entity, Option<&'static Name>), With<ActiveRegion>>,
+    active: Query<'w, 's, (), With<Active>>,
+}
+
+#[derive(SystemParam)]
+struct InspectorStats<'w> {
+    active: Option<Res<'w, ActiveEntities>>,
+    updates: Res<'w, VaneUpdates>,
+    registry: Option<Res<'w, FlowLayerRegistry>>,
+}
+
+fn show_inspector(
+    mut contexts: EguiContexts,
+    mut inspector: ResMut<WindInspector>,
+    stats: InspectorStats,
+    mut flows: ParamSet<(FlowSampler, Query<InspectedFlow, With<Flow>>)>,
+    regions: InspectedRegions,
+    vanes: Query<InspectedVane, With<Vane>>,
+) {
+    if !inspector.open {
+        return;
+    }
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+
+    // The sampler reads the influences and layers edited below, so it samples up front.
+    let centers: Vec<_> = flows
+        .p1()
+        .iter()
+        .map(|flow| (flow.entity, flow.transform.translation(), *flow.layers))
+        .collect();
+    let velocities: EntityHashMap<Vec3> = {
+        let sampler = flows.p0();
+        centers
+            .into_iter()
+            .map(|(entity, center, layers)| (entity, sampler.sample(center, layers).velocity()))
+            .collect()
+    };
+    let mut flows = flows.p1();
+    let registry = stats.registry.as_deref();
+
+    let mut open = inspector.open;
+    egui::Window::new("Wind").open(&mut open).show(ctx, |ui| {
+        let sampling = vanes.iter().filter(|vane| !vane.samples.0.is_empty());
+        ui.label(format!(
+            "{} flows, {} vanes ({} sampling, {} updated this frame)",
+            velocities.len(),
+            vanes.iter().len(),
+            sampling.count(),
+            stats.updates.len(),
+        ));
+        if let Some(active) = &stats.active {
+            ui.label(format!("{} entities in active regions", active.len()));
+        }
+
+        ui.separator();
+        for (region, name, contains) in &regions.regions {
+            let count = contains.map_or(0, |contains| contains.len());
+            let heading = format!("{} ({count} flows)", label("Region", region, name));
+            ui.collapsing(heading, |ui| {
+                for &entity in contains.iter().flat_map(|contains| contains.collection()) {
+                    if let Ok(flow) = flows.get_mut(entity) {
+                        flow_ui(ui, flow, velocities[&entity], registry);
+                    }
+                }
+            });
+        }
+        ui.collapsing("Global flows", |ui| {
+            for flow in &mut flows {
+                if flow.region.is_none() {
+                    let velocity = velocities[&flow.entity];
+                    flow_ui(ui, flow, velocity, registry);
+                }
+            }
+        });
+
+        if let Some(entities) = &stats.active {
+            ui.separator();
+            ui.collapsing("Active regions", |ui| {
+                for (region, name) in &regions.active_regions {
+                    let inside = entities.in_region(region);
+                    let (inside, active) = inside.fold((0, 0), |(inside, active), entity| {
+                        (
+                            inside + 1,
+                            active + regions.active.contains(entity) as usize,
+                        )
+                    });
+                    ui.label(format!(
+                        "{}: {inside} inside, {active} active",
+                        label("Active region", region, name)
+                    ));
+                }
+            });
+        }
+
+        ui.separator();
+        ui.collapsing("Sampling vanes", |ui| {
+            for vane in &vanes {
+                if !vane.samples.0.is_empty() {
+                    vane_ui(ui, vane);
+                }
+            }
+        });
+    });
+    if !open {
+        inspector.open = false;
+    }
+}
+
+fn label(kind: &str, entity: Entity, name: Option<&Name>) -> String {
+    match name {
+        Some(name) => format!("{kind} {name} ({entity})"),
+        None => format!("{kind} {entity}"),
+    }
+}
+
+fn flow_ui(
+    ui: &mut egui::Ui,
+    mut flow: InspectedFlowItem,
+    velocity: Vec3,
+    registry: Option<&FlowLayerRegistry>,
+) {
+    ui.push_id(flow.entity, |ui| {
+        let mut heading = label("Flow", flow.entity, flow.name);
+        if is_inactive(flow.activity) {
+            heading.push_str(" (inactive)");
+        }
+        ui.strong(heading);
+        let field = flow.flow.field.path().map_or_else(
+            || format!("{:?}", flow.flow.field.id()),
+            |path| path.to_string(),
+        );
+        ui.label(format!("Field: {field}"));
+        ui.label(format!("Velocity at center: {velocity:.2}"));
+
+        // Edited through copies, so the flow only looks changed when a value is.
+        let mut influence = flow.influence.0;
+        ui.horizontal(|ui| {
+            ui.label("Influence");
+            if ui
+                .add(egui::DragValue::new(&mut influence).speed(0.01))
+                .changed()
+            {
+                flow.influence.0 = influence;
+            }
+        });
+        layers_ui(ui, &mut flow.layers, registry);
+    });
+}
+
+fn layers_ui(
+    ui: &mut egui::Ui,
+    layers: &mut Mut<FlowLayers>,
+    registry: Option<&FlowLayerRegistry>,
+) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Layers");
+        for layer in 0..FlowLayers::COUNT {
+            let name = registry.and_then(|registry| registry.name(layer));
+            if layer >= 8 && name.is_none() {
+                continue;
+            }
+            let mut on = layers.contains(layer);
+            let text = name.map_or_else(|| layer.to_string(), str::to_owned);
+            if ui.checkbox(&mut on, text).changed() {
+                **layers = if on {
+                    layers.with(layer)
+                } else {
+                    layers.without(layer)
+                };
+            }
+        }
+    });
+}
+
+fn vane_ui(ui: &mut egui::Ui, vane: InspectedVaneItem) {
+    ui.strong(label("Vane", vane.entity, vane.name));
+    ui.label(format!(
+        "Mean velocity: {:.2} over {} samples",
+        vane.samples.mean().velocity(),
+        vane.samples.0.len()
+    ));
+    if let Some(speed) = vane.speed {
+        ui.label(format!("Wind speed: {:.2} m/s", speed.0));
+    }
+    if let Some(velocity) = vane.velocity {
+        ui.label(format!("Wind velocity: {:.2}", velocity.0));
+    }
+    if let Some(pressure) = vane.pressure {
+        ui.label(format!("Dynamic pressure: {:.2} Pa", pressure.0));
+    }
+    if let Some(vorticity) = vane.vorticity {
+        ui.label(format!("Vorticity: {:.2}", vorticity.0));
+    }
+}
+
+fn show_measure_texts<M: Measure<Output: fmt::Debug>>(
+    mut contexts: EguiContexts,
+    cameras: Query<
+        (&Camera, &GlobalTransform, Option<&EguiContextSettings>),
+        With<PrimaryEguiContext>,
+    >,
+    vanes: Query<(Entity, &DebugMeasureText<M>, &GlobalTransform, &Measured<M>)>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    let Ok((camera, camera_transform, settings)) = cameras.single() else {
+        return;
+    };
+    let scale = settings.map_or(1.0, |settings| settings.scale_factor);
+    let origin = camera
+        .logical_viewport_rect()
+        .map_or(Vec2::ZERO, |rect| rect.min);
+    let type_name = any::type_name::<M>();
+    let type_name = type_name.rsplit("::").next().unwrap_or(type_name);
+    for (entity, text, transform, measured) in &vanes {
+        let position = transform.translation() + text.offset;
+        let Ok(viewport) = camera.world_to_viewport(camera_transform, position) else {
+            continue;
+        };
+        let screen = (origin + viewport) / scale;
+        let label = text.label.as_deref().unwrap_or(type_name);
+        egui::Area::new(egui::Id::new((entity, type_name)))
+            .fixed_pos(egui::pos2(screen.x, screen.y))
+            .pivot(egui::Align2::CENTER_BOTTOM)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{label}: {:.2?}", measured.0));
+            });
+    }
+}
@@ -1,3 +1,55 @@
+#![allow(clippy::type_complexity)]
+
+/// Enters a `tracing` span named `$name` until the end of the enclosing scope, when the `trace`
+/// feature is enabled.
+macro_rules! trace_span {
+    ($name:literal) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!($name).entered();
+    };
+}
+
+pub mod activity;
+pub mod aero;
+pub mod ambient;
+pub mod animated;
+#[cfg(feature = "animation")]
+pub mod animation;
+pub mod audio;
+pub mod bounds;
+pub mod builder;
+pub mod capture;
+pub mod coverage;
+#[cfg(feature = "debug-gizmos")]
+pub mod debug;
+pub mod drive;
+pub mod envelope;
+pub mod error;
+pub mod field;
+pub mod flow;
+pub mod generated;
+pub mod gust;
+#[cfg(feature = "hanabi")]
+pub mod hanabi;
+pub mod impulse;
+#[cfg(feature = "egui")]
+pub mod inspector;
+pub mod live;
+pub mod measure;
+pub mod occluder;
+pub mod points;
+pub mod region;
+pub mod sampler;
+pub mod solver;
+pub mod streaming;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod units;
+pub mod vane;
+mod visibility;
+pub mod volume;
+pub mod weather;
+
 use bevy_app::{PluginGroup, PluginGroupBuilder};
 
 pub struct VanePlugins;
@@ -5,6 +57,23 @@ pub struct VanePlugins;
 impl PluginGroup for VanePlugins {
     fn build(self) -> PluginGroupBuilder {
         let mut plugin_group = PluginGroupBuilder::start::<Self>();
+        plugin_group = plugin_group
+            .add(flow::FlowPlugin::default())
+            .add(activity::ActivityPlugin::default())
+            .add(vane::VanePlugin)
+            .add(drive::DrivePlugin)
+            .add(aero::AeroPlugin)
+            .add(impulse::FlowImpulsePlugin)
+            .add(solver::SolverPlugin)
+            .add(envelope::EnvelopePlugin)
+            .add(region::RegionPlugin)
+            .add(streaming::StreamingPlugin)
+            .add(generated::GeneratedFlowPlugin)
+            .add(animated::AnimatedFlowPlugin)
+            .add(capture::FlowCapturePlugin)
+            .add(coverage::RegionCoveragePlugin)
+            .add(measure::MeasuresPlugin)
+            .add(live::FlowFieldStreamPlugin);
         plugin_group
     }
 }
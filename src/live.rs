@@ -0,0 +1,160 @@
+//! A live link for pushing field data from outside the app, such as an offline CFD tool or a
+//! companion simulation running on its own thread or process.
+
+use std::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{AssetId, Assets};
+use bevy_ecs::prelude::{IntoScheduleConfigs, Res, ResMut, Resource};
+use bevy_math::UVec3;
+
+use crate::{
+    error::VaneErrorPolicy,
+    field::FlowField,
+    flow::{FlowSystems, FlowVector},
+};
+
+/// New data for an existing [`FlowField`] asset.
+#[derive(Clone, Debug)]
+pub enum FieldUpdate {
+    /// Replaces the whole field, which may change its size.
+    Full {
+        target: AssetId<FlowField>,
+        field: FlowField,
+    },
+    /// Overwrites a box of texels starting at `offset`, with `data` laid out like a field of
+    /// `size`.
+    Tile {
+        target: AssetId<FlowField>,
+        offset: UVec3,
+        size: UVec3,
+        data: Vec<FlowVector>,
+    },
+}
+
+impl FieldUpdate {
+    pub fn target(&self) -> AssetId<FlowField> {
+        match self {
+            Self::Full { target, .. } | Self::Tile { target, .. } => *target,
+        }
+    }
+}
+
+/// Receives [`FieldUpdate`]s from any thread and applies them to their fields each frame, before
+/// flows are updated.
+///
+/// Hand a [`FlowFieldSender`] to the producer, which pushes updates as they arrive. All updates
+/// queued since the last frame are applied in order. Updates for missing fields and tiles that
+/// don't fit are reported through the [`VaneErrorPolicy`].
+#[derive(Resource)]
+pub struct FlowFieldStream {
+    sender: Sender<FieldUpdate>,
+    receiver: Mutex<Receiver<FieldUpdate>>,
+}
+
+impl Default for FlowFieldStream {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+impl FlowFieldStream {
+    pub fn sender(&self) -> FlowFieldSender {
+        FlowFieldSender(self.sender.clone())
+    }
+}
+
+/// The producing end of a [`FlowFieldStream`]. Cheap to clone and safe to send to other threads.
+#[derive(Clone, Debug)]
+pub struct FlowFieldSender(Sender<FieldUpdate>);
+
+impl FlowFieldSender {
+    /// Queues an update. Returns it back if the stream has been removed from the app.
+    pub fn send(&self, update: FieldUpdate) -> Result<(), FieldUpdate> {
+        self.0.send(update).map_err(|error| error.0)
+    }
+
+    pub fn send_full(
+        &self,
+        target: impl Into<AssetId<FlowField>>,
+        field: FlowField,
+    ) -> Result<(), FieldUpdate> {
+        self.send(FieldUpdate::Full {
+            target: target.into(),
+            field,
+        })
+    }
+
+    pub fn send_tile(
+        &self,
+        target: impl Into<AssetId<FlowField>>,
+        offset: UVec3,
+        size: UVec3,
+        data: Vec<FlowVector>,
+    ) -> Result<(), FieldUpdate> {
+        self.send(FieldUpdate::Tile {
+            target: target.into(),
+            offset,
+            size,
+            data,
+        })
+    }
+}
+
+pub struct FlowFieldStreamPlugin;
+
+impl Plugin for FlowFieldStreamPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlowFieldStream>()
+            .add_systems(PostUpdate, apply_field_updates.before(FlowSystems));
+    }
+}
+
+fn apply_field_updates(
+    stream: Res<FlowFieldStream>,
+    policy: Res<VaneErrorPolicy>,
+    mut fields: ResMut<Assets<FlowField>>,
+) {
+    trace_span!("vane::stream_fields");
+    let receiver = stream.receiver.lock().unwrap();
+    for update in receiver.try_iter() {
+        let target = update.target();
+        let Some(field) = fields.get_mut(target) else {
+            policy.report(format_args!(
+                "streamed update targets missing field {target}"
+            ));
+            continue;
+        };
+        match update {
+            FieldUpdate::Full { field: new, .. } => *field = new,
+            FieldUpdate::Tile {
+                offset, size, data, ..
+            } => {
+                let fits = (offset + size).cmple(field.size()).all()
+                    && data.len() == size.element_product() as usize;
+                if !fits {
+                    policy.report(format_args!(
+                        "streamed tile of size {size} at {offset} doesn't fit field {target}"
+                    ));
+                    continue;
+                }
+                let mut values = data.into_iter();
+                for z in 0..size.z {
+                    for y in 0..size.y {
+                        for x in 0..size.x {
+                            let value = values.next().unwrap();
+                            field.set(offset + UVec3::new(x, y, z), value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! Quantities computed from vane samples, such as wind speed at a weather station or the dynamic
+//! pressure on a sign.
+//!
+//! A typed [`Measure`] is a component added next to a [`Vane`], with a [`MeasurePlugin`] per
+//! type. For measures only known at runtime, such as those created by scripts or editors, register
+//! a [`DynMeasure`] by name in the [`MeasureRegistry`] and list it in a vane's [`DynMeasures`].
+//!
+//! Threads outside the ECS, such as audio callbacks, read measures through a
+//! [`MeasureSnapshot`] taken from [`LatestMeasures`], and other entities through a [`VaneProxy`].
+
+use core::{fmt, marker::PhantomData};
+use std::{collections::HashMap, sync::Arc};
+
+use arc_swap::ArcSwap;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::{
+    Commands, Component, Entity, IntoScheduleConfigs, Query, ReflectComponent, Res, Resource,
+};
+use bevy_math::Vec3;
+use bevy_reflect::{PartialReflect, Reflect, std_traits::ReflectDefault};
+use bevy_time::Time;
+
+use crate::{
+    flow::{FlowLayers, FlowVector},
+    vane::{LayerSamples, Vane, VaneSamples, VaneSystems},
+};
+
+/// A quantity computed from the samples of a [`Vane`] every frame.
+///
+/// Add the measure to the vane's entity and its latest result appears in [`Measured<Self>`].
+pub trait Measure: Component {
+    type Output: Clone + Send + Sync + 'static;
+
+    fn measure(&self, samples: &VaneSamples) -> Self::Output;
+}
+
+/// The latest result of the measure `M` on this entity.
+///
+/// Reflected when both the measure and its output are, so tools can read it through the type
+/// registry. The built-in measures' results are registered by the [`MeasuresPlugin`].
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Measured<M: Measure>(pub M::Output);
+
+impl<M: Measure> Clone for Measured<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<M: Measure<Output: fmt::Debug>> fmt::Debug for Measured<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Measured").field(&self.0).finish()
+    }
+}
+
+/// Takes the measure `M` on every vane that has it. The built-in measures are added by the
+/// [`MeasuresPlugin`].
+pub struct MeasurePlugin<M>(PhantomData<M>);
+
+impl<M> Default for MeasurePlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Measure> Plugin for MeasurePlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                take_measures::<M>,
+                take_layer_measures::<M>,
+                update_proxies::<M>.after(take_measures::<M>),
+            )
+                .in_set(VaneSystems::Measure),
+        );
+    }
+}
+
+fn take_measures<M: Measure>(
+    mut measures: Query<(Entity, &M, &VaneSamples, Option<&mut Measured<M>>)>,
+    mut commands: Commands,
+) {
+    trace_span!("vane::measure");
+    for (entity, measure, samples, measured) in &mut measures {
+        let value = measure.measure(samples);
+        match measured {
+            Some(mut measured) => measured.0 = value,
+            None => {
+                commands.entity(entity).insert(Measured::<M>(value));
+            }
+        }
+    }
+}
+
+/// Mirrors the latest [`Measured<M>`] of `vane` on another entity, such as a windmill that reads
+/// a weather station, so gameplay doesn't need to hold on to the vane itself.
+///
+/// Updated by the [`MeasurePlugin<M>`] whenever the vane has samples. While it has none, such as
+/// when it is hidden, inactive, or despawned, the last value is kept and [`age`](Self::age) grows.
+#[derive(Component)]
+pub struct VaneProxy<M: Measure> {
+    pub vane: Entity,
+    value: Option<M::Output>,
+    age: f32,
+}
+
+impl<M: Measure> VaneProxy<M> {
+    pub fn new(vane: Entity) -> Self {
+        Self {
+            vane,
+            value: None,
+            age: f32::INFINITY,
+        }
+    }
+
+    /// The latest value, or `None` until the vane first has one.
+    pub fn value(&self) -> Option<&M::Output> {
+        self.value.as_ref()
+    }
+
+    /// Seconds since the value was last updated, infinite if it never was.
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+
+    /// Whether the value is missing or older than `max_age` seconds.
+    pub fn is_stale(&self, max_age: f32) -> bool {
+        self.age > max_age
+    }
+}
+
+impl<M: Measure> Clone for VaneProxy<M> {
+    fn clone(&self) -> Self {
+        Self {
+            vane: self.vane,
+            value: self.value.clone(),
+            age: self.age,
+        }
+    }
+}
+
+impl<M: Measure<Output: fmt::Debug>> fmt::Debug for VaneProxy<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VaneProxy")
+            .field("vane", &self.vane)
+            .field("value", &self.value)
+            .field("age", &self.age)
+            .finish()
+    }
+}
+
+fn update_proxies<M: Measure>(
+    time: Res<Time>,
+    mut proxies: Query<&mut VaneProxy<M>>,
+    vanes: Query<(&Measured<M>, &VaneSamples)>,
+) {
+    for mut proxy in &mut proxies {
+        match vanes.get(proxy.vane) {
+            Ok((measured, samples)) if !samples.0.is_empty() => {
+                proxy.value = Some(measured.0.clone());
+                proxy.age = 0.0;
+            }
+            _ => proxy.age += time.delta_secs(),
+        }
+    }
+}
+
+/// Takes the measure `M` separately for each group of the vane's [`LayerSamples`], with results
+/// in [`MeasuredPerLayer<M>`].
+///
+/// Taken by the same [`MeasurePlugin<M>`] as `M` itself, so any measure can be wrapped.
+#[derive(Component, Clone, Debug, Default)]
+#[require(LayerSamples)]
+pub struct PerLayer<M: Measure>(pub M);
+
+/// The latest results of the measure `M` for each layer group of a [`PerLayer<M>`] vane, in the
+/// order of its [`LayerSamples`].
+#[derive(Component)]
+pub struct MeasuredPerLayer<M: Measure>(pub Vec<(FlowLayers, M::Output)>);
+
+impl<M: Measure> MeasuredPerLayer<M> {
+    /// The result for the group `layers`, if it is one of the groups.
+    pub fn get(&self, layers: FlowLayers) -> Option<&M::Output> {
+        self.0
+            .iter()
+            .find(|(group, _)| *group == layers)
+            .map(|(_, output)| output)
+    }
+}
+
+impl<M: Measure> Clone for MeasuredPerLayer<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<M: Measure<Output: fmt::Debug>> fmt::Debug for MeasuredPerLayer<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MeasuredPerLayer").field(&self.0).finish()
+    }
+}
+
+fn take_layer_measures<M: Measure>(
+    mut measures: Query<(
+        Entity,
+        &PerLayer<M>,
+        &LayerSamples,
+        Option<&mut MeasuredPerLayer<M>>,
+    )>,
+    mut commands: Commands,
+) {
+    trace_span!("vane::measure");
+    for (entity, per_layer, layer_samples, measured) in &mut measures {
+        let values = layer_samples
+            .iter()
+            .map(|(layers, samples)| (layers, per_layer.0.measure(samples)));
+        match measured {
+            Some(mut measured) => {
+                measured.0.clear();
+                measured.0.extend(values);
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(MeasuredPerLayer::<M>(values.collect()));
+            }
+        }
+    }
+}
+
+/// The mean flow velocity over a vane's samples, in m/s.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct WindVelocity;
+
+impl Measure for WindVelocity {
+    type Output = Vec3;
+
+    fn measure(&self, samples: &VaneSamples) -> Vec3 {
+        samples.mean().velocity()
+    }
+}
+
+/// The speed of the mean flow over a vane's samples, in m/s.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct WindSpeed;
+
+impl Measure for WindSpeed {
+    type Output = f32;
+
+    fn measure(&self, samples: &VaneSamples) -> f32 {
+        samples.mean().velocity().length()
+    }
+}
+
+/// The dynamic pressure `½ρv²` of the mean flow over a vane's samples, in pascals.
+///
+/// Measures don't see the [`VaneUnits`](crate::units::VaneUnits), so this is only in pascals with
+/// SI units. With others, it mixes the sampled density in kg/m³ with velocities in app units.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct DynamicPressure;
+
+impl Measure for DynamicPressure {
+    type Output = f32;
+
+    fn measure(&self, samples: &VaneSamples) -> f32 {
+        let flow = samples.mean();
+        0.5 * flow.density * flow.velocity().length_squared()
+    }
+}
+
+/// The mean vorticity over a vane's samples, in radians per second about each world axis.
+///
+/// Needs a [`VaneGradient`](crate::vane::VaneGradient) on the vane, and is zero without one.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct Vorticity;
+
+impl Measure for Vorticity {
+    type Output = Vec3;
+
+    fn measure(&self, samples: &VaneSamples) -> Vec3 {
+        let (sum, count) = samples
+            .0
+            .iter()
+            .filter_map(|sample| sample.vorticity())
+            .fold((Vec3::ZERO, 0), |(sum, count), vorticity| {
+                (sum + vorticity, count + 1)
+            });
+        sum / count.max(1) as f32
+    }
+}
+
+/// A measure whose result is a reflected value, so it can be created and read without knowing its
+/// type at compile time.
+///
+/// Implemented for closures, and for any [`Measure`] through [`MeasureRegistry::register_measure`].
+pub trait DynMeasure: Send + Sync + 'static {
+    fn measure(&self, samples: &VaneSamples) -> Box<dyn PartialReflect>;
+}
+
+impl<F> DynMeasure for F
+where
+    F: Fn(&VaneSamples) -> Box<dyn PartialReflect> + Send + Sync + 'static,
+{
+    fn measure(&self, samples: &VaneSamples) -> Box<dyn PartialReflect> {
+        self(samples)
+    }
+}
+
+/// Dynamic measures by name, taken on every vane listing them in its [`DynMeasures`].
+#[derive(Resource, Clone, Default)]
+pub struct MeasureRegistry {
+    measures: HashMap<String, Arc<dyn DynMeasure>>,
+}
+
+impl MeasureRegistry {
+    /// Registers `measure` under `name`, replacing any measure already registered under it.
+    pub fn register(&mut self, name: impl Into<String>, measure: impl DynMeasure) -> &mut Self {
+        self.measures.insert(name.into(), Arc::new(measure));
+        self
+    }
+
+    /// Registers a typed [`Measure`] under `name`, reflecting its output.
+    pub fn register_measure<M>(&mut self, name: impl Into<String>, measure: M) -> &mut Self
+    where
+        M: Measure<Output: PartialReflect>,
+    {
+        self.register(name, move |samples: &VaneSamples| {
+            Box::new(measure.measure(samples)) as Box<dyn PartialReflect>
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn DynMeasure> {
+        self.measures.get(name).map(|measure| &**measure)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.measures.remove(name).is_some()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.measures.keys().map(String::as_str)
+    }
+}
+
+/// The names of the [`MeasureRegistry`] entries to take on this vane. Results appear in
+/// [`DynMeasured`]; names with no registered measure are skipped.
+#[derive(Component, Clone, Debug, Default)]
+#[require(Vane, DynMeasured)]
+pub struct DynMeasures(pub Vec<String>);
+
+/// The latest results of a vane's [`DynMeasures`], by name.
+#[derive(Component, Debug, Default)]
+pub struct DynMeasured(HashMap<String, Box<dyn PartialReflect>>);
+
+impl DynMeasured {
+    pub fn get(&self, name: &str) -> Option<&dyn PartialReflect> {
+        self.0.get(name).map(|value| &**value)
+    }
+
+    /// The result of the measure `name`, if it has one of type `T`.
+    pub fn get_as<T: PartialReflect>(&self, name: &str) -> Option<&T> {
+        self.get(name)?.try_downcast_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &dyn PartialReflect)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), &**value))
+    }
+}
+
+/// Publishes a vane's results in the [`MeasureSnapshot`] under a name.
+#[derive(Component, Clone, Debug)]
+#[require(Vane)]
+pub struct InSnapshot(pub String);
+
+/// The results of every [`InSnapshot`] vane at the end of a frame's measuring.
+#[derive(Debug, Default)]
+pub struct MeasureSnapshot {
+    vanes: HashMap<String, VaneSnapshot>,
+}
+
+impl MeasureSnapshot {
+    pub fn get(&self, name: &str) -> Option<&VaneSnapshot> {
+        self.vanes.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &VaneSnapshot)> {
+        self.vanes.iter().map(|(name, vane)| (name.as_str(), vane))
+    }
+}
+
+/// A vane's entry in a [`MeasureSnapshot`].
+#[derive(Debug)]
+pub struct VaneSnapshot {
+    /// The mean flow over the vane's samples.
+    pub mean: FlowVector,
+    measures: HashMap<String, Box<dyn PartialReflect>>,
+}
+
+impl VaneSnapshot {
+    /// The result of the vane's [`DynMeasures`] entry `name`.
+    pub fn get(&self, name: &str) -> Option<&dyn PartialReflect> {
+        self.measures.get(name).map(|value| &**value)
+    }
+
+    pub fn get_as<T: PartialReflect>(&self, name: &str) -> Option<&T> {
+        self.get(name)?.try_downcast_ref()
+    }
+}
+
+/// Shares the latest [`MeasureSnapshot`] with other threads.
+///
+/// Clone this resource and hand it to a thread, which calls [`load`](Self::load) whenever it
+/// wants current values. Each frame a new snapshot replaces the old one with an atomic pointer
+/// swap, so loading takes no lock and is safe from audio callbacks. Readers never see a snapshot
+/// change while they hold it.
+#[derive(Resource, Clone)]
+pub struct LatestMeasures(Arc<ArcSwap<MeasureSnapshot>>);
+
+impl Default for LatestMeasures {
+    fn default() -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(MeasureSnapshot::default())))
+    }
+}
+
+impl LatestMeasures {
+    pub fn load(&self) -> Arc<MeasureSnapshot> {
+        self.0.load_full()
+    }
+
+    fn store(&self, snapshot: MeasureSnapshot) {
+        self.0.store(Arc::new(snapshot));
+    }
+}
+
+/// Adds the [`MeasureRegistry`] and takes the built-in and dynamic measures.
+pub struct MeasuresPlugin;
+
+impl Plugin for MeasuresPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeasureRegistry>()
+            .init_resource::<LatestMeasures>()
+            .register_type::<WindVelocity>()
+            .register_type::<WindSpeed>()
+            .register_type::<DynamicPressure>()
+            .register_type::<Vorticity>()
+            .register_type::<Measured<WindVelocity>>()
+            .register_type::<Measured<WindSpeed>>()
+            .register_type::<Measured<DynamicPressure>>()
+            .register_type::<Measured<Vorticity>>()
+            .add_plugins((
+                MeasurePlugin::<WindVelocity>::default(),
+                MeasurePlugin::<WindSpeed>::default(),
+                MeasurePlugin::<DynamicPressure>::default(),
+                MeasurePlugin::<Vorticity>::default(),
+            ))
+            .add_systems(
+                PostUpdate,
+                (take_dyn_measures, publish_snapshot)
+                    .chain()
+                    .in_set(VaneSystems::Measure),
+            );
+    }
+}
+
+fn take_dyn_measures(
+    registry: Res<MeasureRegistry>,
+    mut vanes: Query<(&DynMeasures, &VaneSamples, &mut DynMeasured)>,
+) {
+    trace_span!("vane::dyn_measure");
+    for (names, samples, mut measured) in &mut vanes {
+        measured.0.retain(|name, _| names.0.contains(name));
+        for name in &names.0 {
+            if let Some(measure) = registry.measures.get(name) {
+                measured.0.insert(name.clone(), measure.measure(samples));
+            }
+        }
+    }
+}
+
+fn publish_snapshot(
+    latest: Res<LatestMeasures>,
+    vanes: Query<(&InSnapshot, &VaneSamples, Option<&DynMeasured>)>,
+) {
+    trace_span!("vane::publish_snapshot");
+    let vanes = vanes
+        .iter()
+        .map(|(name, samples, measured)| {
+            let measures = measured
+                .into_iter()
+                .flat_map(|measured| &measured.0)
+                .map(|(name, value)| {
+                    let value = value
+                        .reflect_clone()
+                        .map_or_else(|_| value.to_dynamic(), PartialReflect::into_partial_reflect);
+                    (name.clone(), value)
+                })
+                .collect();
+            let vane = VaneSnapshot {
+                mean: samples.mean(),
+                measures,
+            };
+            (name.0.clone(), vane)
+        })
+        .collect();
+    latest.store(MeasureSnapshot { vanes });
+}
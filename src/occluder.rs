@@ -0,0 +1,53 @@
+use bevy_ecs::prelude::{Component, ReflectComponent};
+use bevy_math::{Affine3A, Vec3};
+use bevy_reflect::Reflect;
+use bevy_transform::components::Transform;
+
+use crate::bounds::WorldToLocal;
+
+/// Shelters the flow behind the entity, so that standing behind a wall keeps the wind off.
+///
+/// The occluder is the unit cube `[-0.5, 0.5]³` in the entity's local space. When sampling, the
+/// cube is swept downwind along the local flow direction for `length` meters, and the momentum
+/// of samples inside that shadow is reduced by `strength`, recovering linearly with distance from
+/// the occluder. Density is left untouched.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Transform, WorldToLocal)]
+pub struct WindOccluder {
+    /// How far the shadow reaches downwind, in meters.
+    pub length: f32,
+    /// Fraction of momentum removed directly behind the occluder, from `0` to `1`.
+    pub strength: f32,
+}
+
+impl WindOccluder {
+    /// The factor this occluder scales momentum by at `position`, given the world-space flow
+    /// `direction` there and the inverse of the occluder's transform.
+    pub(crate) fn shelter(&self, inverse: &Affine3A, position: Vec3, direction: Vec3) -> f32 {
+        let origin = inverse.transform_point3(position);
+        let upwind = inverse.transform_vector3(-direction);
+
+        // Slab test of the upwind ray against the unit cube, in world units along `direction`.
+        let mut entry = 0.0f32;
+        let mut exit = self.length;
+        for axis in 0..3 {
+            if upwind[axis].abs() < f32::EPSILON {
+                if origin[axis].abs() > 0.5 {
+                    return 1.0;
+                }
+                continue;
+            }
+            let a = (-0.5 - origin[axis]) / upwind[axis];
+            let b = (0.5 - origin[axis]) / upwind[axis];
+            entry = entry.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+        if entry > exit {
+            return 1.0;
+        }
+
+        let falloff = 1.0 - entry / self.length.max(f32::EPSILON);
+        1.0 - self.strength.clamp(0.0, 1.0) * falloff
+    }
+}
@@ -0,0 +1,63 @@
+use bevy_app::{App, PostUpdate};
+use bevy_ecs::prelude::{Component, Entity, Event, EventWriter, IntoScheduleConfigs, Query};
+use bevy_math::Vec3;
+
+use crate::{
+    flow::{FlowLayers, FlowVector},
+    sampler::FlowSampler,
+    vane::VaneSystems,
+};
+
+/// A component describing a set of points that want flow samples every update, such as the
+/// particles of a cloth or softbody.
+///
+/// Register implementors with [`FlowPointSetAppExt::register_flow_point_set`]. Every update, each
+/// entity with the component receives a [`FlowFieldSamplesReady`] event holding one sample per
+/// point.
+pub trait FlowPointSet: Component {
+    /// The world-space positions to sample, in a stable order.
+    fn flow_points(&self) -> impl Iterator<Item = Vec3>;
+
+    /// The layers to sample from.
+    fn flow_layers(&self) -> FlowLayers {
+        FlowLayers::all()
+    }
+}
+
+/// Batched flow samples for an entity with a registered [`FlowPointSet`].
+#[derive(Event, Clone, Debug)]
+pub struct FlowFieldSamplesReady {
+    pub entity: Entity,
+    /// One sample per point, in the order returned by [`FlowPointSet::flow_points`].
+    pub samples: Vec<FlowVector>,
+}
+
+pub trait FlowPointSetAppExt {
+    /// Samples every `T` alongside vanes and reports the results as [`FlowFieldSamplesReady`].
+    fn register_flow_point_set<T: FlowPointSet>(&mut self) -> &mut Self;
+}
+
+impl FlowPointSetAppExt for App {
+    fn register_flow_point_set<T: FlowPointSet>(&mut self) -> &mut Self {
+        self.add_event::<FlowFieldSamplesReady>().add_systems(
+            PostUpdate,
+            sample_point_sets::<T>.in_set(VaneSystems::Sample),
+        )
+    }
+}
+
+fn sample_point_sets<T: FlowPointSet>(
+    sampler: FlowSampler,
+    point_sets: Query<(Entity, &T)>,
+    mut events: EventWriter<FlowFieldSamplesReady>,
+) {
+    trace_span!("vane::sample_points");
+    for (entity, point_set) in &point_sets {
+        let layers = point_set.flow_layers();
+        let samples = point_set
+            .flow_points()
+            .map(|point| sampler.sample(point, layers))
+            .collect();
+        events.write(FlowFieldSamplesReady { entity, samples });
+    }
+}
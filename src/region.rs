@@ -0,0 +1,368 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::{
+    hierarchy::ChildOf,
+    prelude::{
+        Commands, Component, DetectChangesMut, Entity, Event, EventWriter, IntoScheduleConfigs,
+        Query, ReflectComponent, ResMut, Resource, With, Without, World,
+    },
+    query::{QueryData, QueryFilter, ROQueryItem},
+    relationship::RelationshipTarget,
+    system::SystemParam,
+};
+use bevy_math::{DVec3, Vec3};
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+use bevy_transform::{
+    TransformSystem,
+    components::{GlobalTransform, Transform},
+};
+
+use crate::{
+    activity::{ActivityData, is_inactive},
+    bounds::WorldToLocal,
+    error::InvalidFlow,
+    flow::{Flow, FlowSystems},
+    visibility::{VisibilityData, is_hidden},
+    volume::FlowVolume,
+};
+
+/// A volume that owns a set of flows.
+///
+/// Like a [`Flow`], a region spans the unit cube `[-0.5, 0.5]³` in its local
+/// space, or its [`FlowVolume`] if it has one. Flows join a region with [`InRegion`], and only
+/// contribute to points inside it. Flows outside any region contribute everywhere.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Transform, WorldToLocal)]
+pub struct Region;
+
+/// Places a flow in a [`Region`].
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+#[relationship(relationship_target = Contains)]
+pub struct InRegion(pub Entity);
+
+/// The flows in a [`Region`], which are despawned along with it.
+#[derive(Component, Debug, Default)]
+#[relationship_target(relationship = InRegion, linked_spawn)]
+pub struct Contains(Vec<Entity>);
+
+impl Region {
+    /// The flows in a region, given its [`Contains`].
+    pub fn flows(contains: &Contains) -> impl Iterator<Item = Entity> + '_ {
+        contains.iter()
+    }
+}
+
+/// Looks up the [`Region`]s of flows from a [`World`].
+pub trait RegionWorldExt {
+    /// The region `entity` is in, if any.
+    fn region_of(&self, entity: Entity) -> Option<Entity>;
+
+    /// The flows in `region`, or an empty list if it has none.
+    fn flows_in(&self, region: Entity) -> Vec<Entity>;
+}
+
+impl RegionWorldExt for World {
+    fn region_of(&self, entity: Entity) -> Option<Entity> {
+        self.get::<InRegion>(entity).map(|in_region| in_region.0)
+    }
+
+    fn flows_in(&self, region: Entity) -> Vec<Entity> {
+        self.get::<Contains>(region)
+            .map(|contains| contains.iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Iterates over the flows of a [`Region`] that take part in sampling, along with their
+/// components `D`.
+///
+/// Flows are skipped like in a [`FlowSampler`](crate::sampler::FlowSampler): when they are hidden,
+/// disabled, [`InvalidFlow`]s, or [`SpatialActivity`](crate::activity::SpatialActivity) flows
+/// outside every active region. So are flows that don't match `F`.
+#[derive(SystemParam)]
+pub struct RegionFlows<'w, 's, D: QueryData + 'static, F: QueryFilter + 'static = ()> {
+    regions: Query<'w, 's, &'static Contains>,
+    flows: Query<'w, 's, (D, VisibilityData, ActivityData), (With<Flow>, Without<InvalidFlow>, F)>,
+}
+
+impl<D: QueryData, F: QueryFilter> RegionFlows<'_, '_, D, F> {
+    pub fn iter(&self, region: Entity) -> impl Iterator<Item = ROQueryItem<'_, D>> {
+        self.regions
+            .get(region)
+            .into_iter()
+            .flat_map(|contains| contains.iter())
+            .filter_map(|flow| self.flows.get(flow).ok())
+            .filter(|(_, visibility, activity)| !is_hidden(*visibility) && !is_inactive(*activity))
+            .map(|(item, ..)| item)
+    }
+}
+
+/// Orders overlapping [`Region`]s. Higher priorities win.
+///
+/// The flows of a region are composed after those of lower-priority regions, whatever their
+/// [`FlowBlend`](crate::flow::FlowBlend) order. Flows outside any region have priority `0`.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[reflect(Component, Default)]
+pub struct RegionPriority(pub i32);
+
+/// Makes a [`Region`] mask out everything else inside it, such as an interior that shuts out the
+/// weather outside.
+///
+/// Points inside an exclusive region only see the flows of that region and the regions nested in
+/// it, without the
+/// [`AmbientFlow`](crate::ambient::AmbientFlow). Where exclusive regions overlap, the one with
+/// the highest [`RegionPriority`] wins.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Region)]
+pub struct ExclusiveRegion;
+
+/// Stops a nested [`Region`] from inheriting the flows of the regions it is nested in.
+///
+/// A region parented to another region normally sees its ancestors' flows as well as its own,
+/// even where it pokes outside them or masks everything else as an [`ExclusiveRegion`], so an
+/// interior courtyard can add local swirl on top of the city-wide wind. Inside an isolated
+/// region, its ancestors' flows don't contribute at all.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Region)]
+pub struct IsolatedRegion;
+
+/// Keeps a [`Region`] centered on a target entity, such as the camera or player, so wind only
+/// needs authoring around where it is seen.
+///
+/// The region is placed in world space, axis-aligned, even when it has a parent.
+///
+/// With a `rebase_distance`, the region also acts as a floating origin: once the target strays
+/// that far from the world origin, every root entity is shifted back so the target sits at the
+/// origin again, keeping transforms precise in large worlds. See [`WorldOrigin`] for what is
+/// shifted.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+#[require(Region)]
+pub struct FollowRegion {
+    #[entities]
+    pub target: Entity,
+    /// The size of the region along each axis.
+    pub extent: Vec3,
+    pub rebase_distance: Option<f32>,
+}
+
+impl FollowRegion {
+    pub fn new(target: Entity, extent: Vec3) -> Self {
+        Self {
+            target,
+            extent,
+            rebase_distance: None,
+        }
+    }
+
+    pub fn with_rebase_distance(mut self, distance: f32) -> Self {
+        self.rebase_distance = Some(distance);
+        self
+    }
+}
+
+/// Where the world origin currently lies in absolute coordinates, after rebasing by
+/// [`FollowRegion`]s.
+///
+/// Rebasing shifts the [`Transform`] of every entity without a parent. The rest of the world-space
+/// state, such as flow velocity tracking and sway rest poses, is shifted by observers of
+/// [`OriginRebased`] in the modules that own it. Your own world-space state should be shifted the
+/// same way.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct WorldOrigin(pub DVec3);
+
+/// Sent when a [`FollowRegion`] rebases the world, and triggered for observers before transforms
+/// propagate.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct OriginRebased {
+    /// The translation that was applied to every root entity.
+    pub offset: Vec3,
+}
+
+/// Keeps [`FollowRegion`]s on their targets and rebases the world origin.
+pub struct RegionPlugin;
+
+impl Plugin for RegionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldOrigin>()
+            .register_type::<Region>()
+            .register_type::<InRegion>()
+            .register_type::<RegionPriority>()
+            .register_type::<ExclusiveRegion>()
+            .register_type::<IsolatedRegion>()
+            .register_type::<FollowRegion>()
+            .add_event::<OriginRebased>()
+            .add_systems(
+                PostUpdate,
+                (
+                    rebase_origin.before(TransformSystem::TransformPropagate),
+                    follow_targets
+                        .after(TransformSystem::TransformPropagate)
+                        .before(FlowSystems),
+                ),
+            );
+    }
+}
+
+fn rebase_origin(
+    regions: Query<&FollowRegion>,
+    targets: Query<&GlobalTransform>,
+    mut origin: ResMut<WorldOrigin>,
+    mut roots: Query<&mut Transform, Without<ChildOf>>,
+    mut events: EventWriter<OriginRebased>,
+    mut commands: Commands,
+) {
+    let Some(translation) = regions.iter().find_map(|region| {
+        let distance = region.rebase_distance?;
+        let translation = targets.get(region.target).ok()?.translation();
+        (translation.length() > distance).then_some(translation)
+    }) else {
+        return;
+    };
+
+    let offset = -translation;
+    origin.0 += translation.as_dvec3();
+    for mut transform in &mut roots {
+        transform.translation += offset;
+    }
+    events.write(OriginRebased { offset });
+    commands.trigger(OriginRebased { offset });
+}
+
+fn follow_targets(
+    mut regions: Query<(
+        &FollowRegion,
+        &mut Transform,
+        &mut GlobalTransform,
+        Option<&ChildOf>,
+    )>,
+    targets: Query<&GlobalTransform, Without<FollowRegion>>,
+) {
+    for (region, mut transform, mut global_transform, parent) in &mut regions {
+        let Ok(target) = targets.get(region.target) else {
+            continue;
+        };
+        let bounds = GlobalTransform::from(
+            Transform::from_translation(target.translation()).with_scale(region.extent),
+        );
+        let local = match parent.map(|parent| targets.get(parent.parent())) {
+            Some(Ok(parent)) => bounds.reparented_to(parent),
+            _ => bounds.compute_transform(),
+        };
+        transform.set_if_neq(local);
+        // Propagation has already run this frame.
+        global_transform.set_if_neq(bounds);
+    }
+}
+
+/// Whether the world-space `position` lies inside the region or flow with the given transform
+/// and volume.
+pub(crate) fn contains(
+    world_to_local: &WorldToLocal,
+    volume: Option<&FlowVolume>,
+    position: Vec3,
+) -> bool {
+    let local = world_to_local.0.transform_point3(position);
+    volume.map_or(local.abs().max_element() <= 0.5, |volume| {
+        volume.contains(local)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::SystemState;
+    use bevy_math::Quat;
+
+    use super::*;
+    use crate::{
+        activity::SpatialActivity, drive::FlowSwayState, flow::InheritedVelocity,
+        test_utils::FlowWorldBuilder,
+    };
+
+    #[test]
+    fn region_flows_skip_invalid_and_inactive_flows() {
+        let mut world = FlowWorldBuilder::default();
+        let region = world.region(Transform::default());
+        let flows = [(); 3].map(|_| world.uniform_flow_in(region, Vec3::X, Transform::default()));
+        world.step(1);
+        world.world_mut().entity_mut(flows[1]).insert(InvalidFlow);
+        world
+            .world_mut()
+            .entity_mut(flows[2])
+            .insert(SpatialActivity);
+
+        let mut state = SystemState::<RegionFlows<Entity>>::new(world.world_mut());
+        let region_flows = state.get(world.world());
+        assert_eq!(region_flows.iter(region).collect::<Vec<_>>(), [flows[0]]);
+    }
+
+    #[test]
+    fn rebasing_shifts_world_space_state() {
+        let mut world = FlowWorldBuilder::default();
+        let position = Transform::from_xyz(6.0, 0.0, 0.0);
+        let target = world.world_mut().spawn(position).id();
+        let flow = world.uniform_flow(Vec3::X, position);
+        let sway = world
+            .world_mut()
+            .spawn((
+                FlowSwayState {
+                    rest: Some(position),
+                    ..Default::default()
+                },
+                position,
+            ))
+            .id();
+        world.step(3);
+
+        world
+            .world_mut()
+            .spawn(FollowRegion::new(target, Vec3::splat(10.0)).with_rebase_distance(5.0));
+        world.step(1);
+
+        let world = world.world();
+        assert_eq!(world.resource::<WorldOrigin>().0, DVec3::X * 6.0);
+        let velocity = world.get::<InheritedVelocity>(flow).unwrap();
+        assert!(
+            velocity.linear.length() < 1e-3,
+            "the flow moved at {}",
+            velocity.linear
+        );
+        let rest = world.get::<FlowSwayState>(sway).unwrap().rest.unwrap();
+        assert!(rest.translation.abs_diff_eq(Vec3::ZERO, 1e-4));
+    }
+
+    #[test]
+    fn parented_regions_follow_in_world_space() {
+        let mut world = FlowWorldBuilder::default();
+        let target = world
+            .world_mut()
+            .spawn(Transform::from_xyz(5.0, 0.0, 0.0))
+            .id();
+        let parent = world
+            .world_mut()
+            .spawn(
+                Transform::from_xyz(100.0, 0.0, 0.0)
+                    .with_rotation(Quat::from_rotation_y(1.0))
+                    .with_scale(Vec3::splat(2.0)),
+            )
+            .id();
+        let region = world
+            .world_mut()
+            .spawn((FollowRegion::new(target, Vec3::splat(4.0)), ChildOf(parent)))
+            .id();
+
+        for _ in 0..2 {
+            world.step(1);
+            let bounds = world.world().get::<GlobalTransform>(region).unwrap();
+            assert!(
+                bounds.translation().abs_diff_eq(Vec3::X * 5.0, 1e-4),
+                "the region is centered on {}",
+                bounds.translation()
+            );
+            assert!(bounds.scale().abs_diff_eq(Vec3::splat(4.0), 1e-4));
+        }
+    }
+}
@@ -0,0 +1,578 @@
+use bevy_asset::Assets;
+use bevy_ecs::{
+    hierarchy::ChildOf,
+    prelude::{Entity, Has, Query, Res, With, Without},
+    query::QueryData,
+    system::SystemParam,
+};
+use bevy_math::Vec3;
+use bevy_time::Time;
+use bevy_transform::components::GlobalTransform;
+use smallvec::SmallVec;
+
+use crate::{
+    activity::{ActivityData, is_inactive},
+    ambient::AmbientFlow,
+    bounds::{FlowGrid, WorldToLocal},
+    envelope::FlowEnvelope,
+    error::InvalidFlow,
+    field::FlowField,
+    flow::{
+        FieldCrossfade, Flow, FlowBlend, FlowCrossfade, FlowDrift, FlowExtent, FlowFalloff,
+        FlowFieldStack, FlowInfluence, FlowInfluencePerLayer, FlowLayerInfluence, FlowLayers,
+        FlowRemap, FlowVector, InheritedVelocity,
+    },
+    occluder::WindOccluder,
+    region::{self, ExclusiveRegion, InRegion, IsolatedRegion, Region, RegionPriority},
+    visibility::{VisibilityData, is_hidden},
+    volume::FlowVolume,
+};
+
+/// The components of a flow that take part in sampling.
+#[derive(QueryData)]
+struct SampledFlow {
+    flow: &'static Flow,
+    transform: &'static GlobalTransform,
+    world_to_local: &'static WorldToLocal,
+    influence: &'static FlowInfluence,
+    layer_influence: Option<&'static FlowInfluencePerLayer>,
+    layers: &'static FlowLayers,
+    velocity: &'static InheritedVelocity,
+    falloff: Option<&'static FlowFalloff>,
+    remap: Option<&'static FlowRemap>,
+    blend: Option<&'static FlowBlend>,
+    volume: Option<&'static FlowVolume>,
+    visibility: VisibilityData,
+    activity: ActivityData,
+    envelope: Option<&'static FlowEnvelope>,
+    crossfade: Option<&'static FlowCrossfade>,
+    field_crossfade: Option<&'static FieldCrossfade>,
+    stack: Option<&'static FlowFieldStack>,
+    region: Option<&'static InRegion>,
+    extent: Option<&'static FlowExtent>,
+    drift: Option<&'static FlowDrift>,
+}
+
+/// The flows contributing at a point as `(priority, blend, sample, weight)`. Few flows overlap
+/// at once, so sampling doesn't allocate.
+type Contributions = SmallVec<[(RegionPriority, FlowBlend, FlowVector, f32); 8]>;
+
+/// Regions and their priorities, as found at a point.
+type RegionList = SmallVec<[(Entity, RegionPriority); 4]>;
+
+/// The components of a region that take part in sampling.
+#[derive(QueryData)]
+struct SampledRegion {
+    entity: Entity,
+    world_to_local: &'static WorldToLocal,
+    volume: Option<&'static FlowVolume>,
+    priority: Option<&'static RegionPriority>,
+    exclusive: Has<ExclusiveRegion>,
+    isolated: Has<IsolatedRegion>,
+    parent: Option<&'static ChildOf>,
+}
+
+/// Samples the composed flow at arbitrary points on the CPU.
+///
+/// Flows set to `Visibility::Hidden` are skipped, as are disabled and [`InvalidFlow`]s and
+/// [`SpatialActivity`](crate::activity::SpatialActivity) flows outside every active region. Only the
+/// flows, regions, and occluders the [`FlowGrid`] finds near each point are visited.
+#[derive(SystemParam)]
+pub struct FlowSampler<'w, 's> {
+    flows: Query<'w, 's, SampledFlow, Without<InvalidFlow>>,
+    regions: Query<'w, 's, SampledRegion, With<Region>>,
+    occluders: Query<'w, 's, (&'static WindOccluder, &'static WorldToLocal)>,
+    grid: Res<'w, FlowGrid>,
+    fields: Res<'w, Assets<FlowField>>,
+    ambient: Option<Res<'w, AmbientFlow>>,
+    layer_influence: Option<Res<'w, FlowLayerInfluence>>,
+    time: Res<'w, Time>,
+}
+
+impl FlowSampler<'_, '_> {
+    /// Samples the composition of all flows in `layers` at a world-space `position`.
+    ///
+    /// Composition starts from the [`AmbientFlow`], if any. Each flow is weighted by its
+    /// [`FlowInfluence`], [`FlowInfluencePerLayer`], [`FlowFalloff`], and [`FlowEnvelope`], and
+    /// composed in the order given by its [`FlowBlend`]. Its [`InheritedVelocity`] at `position`
+    /// is added to its field's velocity before any [`FlowRemap`], and the momentum of the result
+    /// is reduced inside the shadows of [`WindOccluder`]s. Flows and the ambient flow are scaled
+    /// by the [`FlowLayerInfluence`] of the layers they share with `layers`.
+    ///
+    /// Flows in a [`Region`] only contribute inside it, after flows of lower [`RegionPriority`].
+    /// Inside an [`ExclusiveRegion`], only the flows of that region and the regions nested in it
+    /// contribute. Flows of the regions a region is nested in also contribute inside it, unless
+    /// it is an [`IsolatedRegion`].
+    pub fn sample(&self, position: Vec3, layers: FlowLayers) -> FlowVector {
+        let (contributions, exclusive) = self.contributions(position, layers, None);
+        let ambient = self
+            .ambient
+            .as_ref()
+            .filter(|ambient| !exclusive && ambient.layers.intersects(&layers))
+            .map_or(FlowVector::ZERO, |ambient| {
+                ambient.sample(position)
+                    * self.layer_multiplier(ambient.layers.intersection(layers))
+            });
+        let mut total = compose(ambient, contributions);
+
+        let direction = total.momentum.normalize_or_zero();
+        if direction != Vec3::ZERO {
+            for occluder in self.grid.query_occluders(position) {
+                let Ok((occluder, world_to_local)) = self.occluders.get(occluder) else {
+                    continue;
+                };
+                total.momentum *= occluder.shelter(world_to_local.affine(), position, direction);
+            }
+        }
+        total
+    }
+
+    /// The flows contributing at `position` as `(priority, blend, sample, weight)`, in
+    /// composition order, and whether the position is inside an [`ExclusiveRegion`].
+    ///
+    /// With `only`, just the flows in that region and the regions nested in it contribute, as
+    /// if it covered every point.
+    fn contributions(
+        &self,
+        position: Vec3,
+        layers: FlowLayers,
+        only: Option<Entity>,
+    ) -> (Contributions, bool) {
+        let (regions, exclusive) = self.regions_at(position, only);
+
+        let elapsed = self.time.elapsed_secs_f64();
+        let mut contributions = Contributions::new();
+        for flow in self.grid.query(position) {
+            let Ok(flow) = self.flows.get(flow) else {
+                continue;
+            };
+            if !flow.layers.intersects(&layers)
+                || is_hidden(flow.visibility)
+                || is_inactive(flow.activity)
+            {
+                continue;
+            }
+            let region = flow.region.map(|in_region| in_region.0);
+            let priority = match region {
+                Some(region) => {
+                    let Some(&(_, priority)) = regions.iter().find(|(r, _)| *r == region) else {
+                        continue;
+                    };
+                    priority
+                }
+                None if exclusive.is_some() || only.is_some() => continue,
+                None => RegionPriority::default(),
+            };
+            let local = flow.world_to_local.affine().transform_point3(position);
+            let inside = match flow.volume {
+                Some(volume) => volume.contains(local),
+                None => local.abs().max_element() <= 0.5,
+            };
+            if !inside {
+                continue;
+            }
+            let shared = flow.layers.intersection(layers);
+            let weight = flow.falloff.map_or(1.0, |falloff| falloff.weight(local))
+                * flow.envelope.map_or(1.0, FlowEnvelope::level)
+                * self.layer_multiplier(shared)
+                * flow
+                    .layer_influence
+                    .map_or(1.0, |influence| influence.multiplier(shared));
+            if weight <= 0.0 {
+                continue;
+            }
+            let Some(field) = self.fields.get(&flow.flow.field) else {
+                continue;
+            };
+            let extent = flow.extent.copied().unwrap_or_default();
+            let scale = flow.transform.scale();
+            let sample_field = |field: &FlowField| match flow.drift {
+                Some(drift) => {
+                    let position = extent.field_position(field, local, scale);
+                    field.sample_wrapped(drift.displace(position, elapsed))
+                }
+                None => extent.sample(field, local, scale),
+            };
+            let faded = flow.field_crossfade.and_then(|fade| {
+                Some((
+                    self.fields.get(&fade.from)?,
+                    self.fields.get(&fade.to)?,
+                    fade.progress(),
+                ))
+            });
+            let mut sample = match faded {
+                Some((from, to, t)) => sample_field(from).lerp(sample_field(to), t),
+                None => sample_field(field),
+            };
+            if let Some((previous, weight)) = flow.crossfade.and_then(FlowCrossfade::fading_out)
+                && let Some(previous) = self.fields.get(previous)
+            {
+                sample = sample.lerp(sample_field(previous), weight);
+            }
+            for layer in flow.stack.iter().flat_map(|stack| &stack.0) {
+                if let Some(field) = self.fields.get(&layer.field) {
+                    sample += sample_field(field) * layer.weight;
+                }
+            }
+            let offset = position - flow.transform.translation();
+            sample.momentum += sample.density * flow.velocity.at(offset);
+            if let Some(remap) = flow.remap {
+                sample = remap.apply(sample);
+            }
+            let blend = flow.blend.copied().unwrap_or_default();
+            contributions.push((priority, blend, sample, flow.influence.0 * weight));
+        }
+
+        contributions.sort_by_key(|&(priority, blend, ..)| (priority, blend.order));
+        (contributions, exclusive.is_some())
+    }
+
+    /// The regions whose flows may contribute at `position`, including those inherited by nested
+    /// regions, and the exclusive region there, if any.
+    ///
+    /// Inside an exclusive region, only it and the regions nested in it contribute. The ancestors
+    /// of isolated regions are masked out, even where other regions inherit them. With `only`,
+    /// just that region, wherever the position is, and those nested in it are considered.
+    fn regions_at(
+        &self,
+        position: Vec3,
+        only: Option<Entity>,
+    ) -> (RegionList, Option<(Entity, RegionPriority)>) {
+        let mut regions = RegionList::new();
+        let mut containing = SmallVec::<[SampledRegionItem; 4]>::new();
+        if let Some(root) = only {
+            match self.regions.get(root) {
+                Ok(root) => containing.push(root),
+                // Flows can join any entity, even one that isn't a region.
+                Err(_) => regions.push((root, RegionPriority::default())),
+            }
+        }
+        let mut exclusive: Option<(Entity, RegionPriority)> = None;
+        for region in self.grid.query_regions(position) {
+            let Ok(region) = self.regions.get(region) else {
+                continue;
+            };
+            if only.is_some_and(|root| region.entity == root || !self.is_nested_in(&region, root))
+                || !region::contains(region.world_to_local, region.volume, position)
+            {
+                continue;
+            }
+            let priority = region.priority.copied().unwrap_or_default();
+            if region.exclusive && exclusive.is_none_or(|(_, best)| priority > best) {
+                exclusive = Some((region.entity, priority));
+            }
+            containing.push(region);
+        }
+
+        let mut masked = RegionList::new();
+        let visible = containing.iter().filter(|region| {
+            exclusive.is_none_or(|(entity, _)| {
+                region.entity == entity || self.is_nested_in(region, entity)
+            })
+        });
+        for region in visible {
+            regions.push((region.entity, region.priority.copied().unwrap_or_default()));
+            let inherited = if region.isolated {
+                &mut masked
+            } else {
+                &mut regions
+            };
+            self.push_ancestors(region, inherited);
+        }
+        regions.retain(|(region, _)| {
+            !masked.iter().any(|(m, _)| m == region)
+                && only.is_none_or(|root| {
+                    *region == root
+                        || self
+                            .regions
+                            .get(*region)
+                            .is_ok_and(|region| self.is_nested_in(&region, root))
+                })
+        });
+        (regions, exclusive)
+    }
+
+    /// Pushes the regions `region` is nested in, up to and including the first isolated one.
+    fn push_ancestors(&self, region: &SampledRegionItem, regions: &mut RegionList) {
+        let mut parent = region.parent;
+        while let Some(child_of) = parent
+            && let Ok(ancestor) = self.regions.get(child_of.parent())
+        {
+            regions.push((
+                ancestor.entity,
+                ancestor.priority.copied().unwrap_or_default(),
+            ));
+            if ancestor.isolated {
+                break;
+            }
+            parent = ancestor.parent;
+        }
+    }
+
+    fn is_nested_in(&self, region: &SampledRegionItem, ancestor: Entity) -> bool {
+        let mut parent = region.parent;
+        while let Some(child_of) = parent {
+            if child_of.parent() == ancestor {
+                return true;
+            }
+            parent = self
+                .regions
+                .get(child_of.parent())
+                .ok()
+                .and_then(|region| region.parent);
+        }
+        false
+    }
+
+    fn layer_multiplier(&self, shared: FlowLayers) -> f32 {
+        self.layer_influence
+            .as_ref()
+            .map_or(1.0, |influence| influence.multiplier(shared))
+    }
+
+    /// Samples only the flows in `region` at `position`, as if the region covered every point,
+    /// without the [`AmbientFlow`] or [`WindOccluder`]s.
+    ///
+    /// The flows of regions nested in `region` contribute inside their own regions, as they
+    /// would in [`sample`](Self::sample).
+    pub fn sample_region(&self, region: Entity, position: Vec3, layers: FlowLayers) -> FlowVector {
+        let (contributions, _) = self.contributions(position, layers, Some(region));
+        compose(FlowVector::ZERO, contributions)
+    }
+
+    /// How many flows contribute at `position`, and their total weighted influence, ignoring the
+    /// [`AmbientFlow`].
+    pub fn coverage(&self, position: Vec3, layers: FlowLayers) -> (usize, f32) {
+        let (contributions, _) = self.contributions(position, layers, None);
+        let influence = contributions.iter().map(|&(.., weight)| weight).sum();
+        (contributions.len(), influence)
+    }
+}
+
+fn compose(start: FlowVector, contributions: Contributions) -> FlowVector {
+    contributions
+        .into_iter()
+        .fold(start, |total, (_, blend, sample, weight)| {
+            blend.mode.blend(total, sample, weight)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::SystemState;
+    use bevy_math::UVec3;
+    use bevy_transform::components::Transform;
+
+    use super::*;
+    use crate::{
+        flow::{AIR_DENSITY, BlendMode},
+        region::{ExclusiveRegion, IsolatedRegion},
+        test_utils::FlowWorldBuilder,
+    };
+
+    fn sample(world: &mut FlowWorldBuilder, position: Vec3) -> FlowVector {
+        let mut state = SystemState::<FlowSampler>::new(world.world_mut());
+        state.get(world.world()).sample(position, FlowLayers::all())
+    }
+
+    fn wide() -> Transform {
+        Transform::from_scale(Vec3::splat(10.0))
+    }
+
+    /// Composes a flow of `top` over one of air at (2, 1, 0) m/s, with its `blend`, and samples
+    /// the velocity where they overlap.
+    fn composed(top: FlowField, influence: f32, blend: FlowBlend) -> Vec3 {
+        let mut world = FlowWorldBuilder::default();
+        world.uniform_flow(Vec3::new(2.0, 1.0, 0.0), wide());
+        let top = world.flow(top, wide());
+        world
+            .world_mut()
+            .entity_mut(top)
+            .insert((blend, FlowInfluence(influence)));
+        world.step(1);
+        sample(&mut world, Vec3::ZERO).velocity()
+    }
+
+    fn air(velocity: Vec3) -> FlowField {
+        FlowField::from_fn(UVec3::ONE, |_| {
+            FlowVector::from_velocity(velocity, AIR_DENSITY)
+        })
+    }
+
+    fn over(mode: BlendMode) -> FlowBlend {
+        FlowBlend { mode, order: 1 }
+    }
+
+    #[track_caller]
+    fn assert_velocity(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-4),
+            "sampled {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn blend_modes_compose_overlapping_flows() {
+        let gust = || air(Vec3::X * 6.0);
+        // Half as much air again, carrying its momentum.
+        let added = composed(gust(), 0.5, over(BlendMode::Add));
+        assert_velocity(added, Vec3::new(10.0, 2.0, 0.0) / 3.0);
+        // The weighted gust carries more momentum than the base flow.
+        let max = composed(gust(), 0.5, over(BlendMode::Max));
+        assert_velocity(max, Vec3::X * 3.0);
+        let overridden = composed(gust(), 0.5, over(BlendMode::Override));
+        assert_velocity(overridden, Vec3::new(4.0, 0.5, 0.0));
+        let added_velocity = composed(gust(), 0.5, over(BlendMode::AddVelocity));
+        assert_velocity(added_velocity, Vec3::new(5.0, 1.0, 0.0));
+
+        let scale = FlowField::from_fn(UVec3::ONE, |_| {
+            FlowVector::new(Vec3::new(2.0, 3.0, 1.0), 1.0)
+        });
+        let multiplied = composed(scale, 1.0, over(BlendMode::Multiply));
+        assert_velocity(multiplied, Vec3::new(4.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn flows_compose_by_priority_then_order() {
+        let gust = || air(Vec3::X * 6.0);
+        let before = FlowBlend {
+            mode: BlendMode::Override,
+            order: -1,
+        };
+        let after = over(BlendMode::Override);
+        assert_velocity(composed(gust(), 1.0, after), Vec3::X * 6.0);
+        // Overriding nothing, then the base flow adds its air to the override's.
+        assert_velocity(composed(gust(), 1.0, before), Vec3::new(4.0, 0.5, 0.0));
+
+        // A higher region priority composes later, whatever the order.
+        let mut world = FlowWorldBuilder::default();
+        world.uniform_flow(Vec3::new(2.0, 1.0, 0.0), wide());
+        let region = world.region(wide());
+        world
+            .world_mut()
+            .entity_mut(region)
+            .insert(RegionPriority(1));
+        let top = world.uniform_flow_in(region, Vec3::X * 6.0, wide());
+        world.world_mut().entity_mut(top).insert(before);
+        world.step(1);
+        assert_velocity(sample(&mut world, Vec3::ZERO).velocity(), Vec3::X * 6.0);
+    }
+
+    #[test]
+    fn exclusive_regions_mask_everything_else() {
+        let mut world = FlowWorldBuilder::default();
+        world
+            .world_mut()
+            .insert_resource(AmbientFlow::uniform(FlowVector::from_velocity(
+                Vec3::NEG_X,
+                AIR_DENSITY,
+            )));
+        world.uniform_flow(Vec3::Z * 3.0, wide());
+        let outer = world.region(wide());
+        world
+            .world_mut()
+            .entity_mut(outer)
+            .insert(RegionPriority(5));
+        world.uniform_flow_in(outer, Vec3::X, wide());
+        // Masks the others even though they have a higher priority.
+        let interior = world.region(Transform::from_scale(Vec3::splat(4.0)));
+        world
+            .world_mut()
+            .entity_mut(interior)
+            .insert(ExclusiveRegion);
+        world.uniform_flow_in(interior, Vec3::Y * 2.0, wide());
+        world.step(1);
+
+        let mut state = SystemState::<FlowSampler>::new(world.world_mut());
+        let sampler = state.get(world.world());
+        let (inside, exclusive) = sampler.contributions(Vec3::ZERO, FlowLayers::all(), None);
+        assert!(exclusive);
+        assert_eq!(inside.len(), 1);
+        assert_velocity(
+            sampler.sample(Vec3::ZERO, FlowLayers::all()).velocity(),
+            Vec3::Y * 2.0,
+        );
+
+        // Outside it, everything else contributes and its flows don't.
+        let (outside, exclusive) = sampler.contributions(Vec3::X * 3.0, FlowLayers::all(), None);
+        assert!(!exclusive);
+        assert_eq!(outside.len(), 2);
+        assert_velocity(
+            sampler.sample(Vec3::X * 3.0, FlowLayers::all()).velocity(),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+    }
+
+    /// Spawns a region nested in `parent`, `size` wide around `x` in its parent's space, with a
+    /// flow of `velocity` in it.
+    fn nested(
+        world: &mut FlowWorldBuilder,
+        parent: Entity,
+        x: f32,
+        size: f32,
+        velocity: Vec3,
+    ) -> Entity {
+        let transform = Transform::from_xyz(x, 0.0, 0.0).with_scale(Vec3::splat(size));
+        let region = world.region(transform);
+        world.world_mut().entity_mut(region).insert(ChildOf(parent));
+        world.uniform_flow_in(region, velocity, wide());
+        region
+    }
+
+    #[test]
+    fn nested_regions_inherit_unless_isolated() {
+        let mut world = FlowWorldBuilder::default();
+        let outer = world.region(Transform::default());
+        world.uniform_flow_in(outer, Vec3::X * 3.0, wide());
+        // In the outer region's space, which is a unit cube.
+        nested(&mut world, outer, -0.3, 0.2, Vec3::Y * 3.0);
+        nested(&mut world, outer, 0.2, 0.4, Vec3::Z * 3.0);
+        let isolated = nested(&mut world, outer, 0.3, 0.2, Vec3::NEG_Y * 3.0);
+        world
+            .world_mut()
+            .entity_mut(isolated)
+            .insert(IsolatedRegion);
+        world.step(1);
+
+        // Each flow carries its own air, so overlapping pairs average out.
+        assert_velocity(
+            sample(&mut world, Vec3::X * -0.3).velocity(),
+            Vec3::new(1.5, 1.5, 0.0),
+        );
+        assert_velocity(
+            sample(&mut world, Vec3::X * 0.1).velocity(),
+            Vec3::new(1.5, 0.0, 1.5),
+        );
+        // The courtyard inherits the outer flows, but the isolated region masks them.
+        assert_velocity(
+            sample(&mut world, Vec3::X * 0.3).velocity(),
+            Vec3::new(0.0, -1.5, 1.5),
+        );
+    }
+
+    #[test]
+    fn isolated_regions_mask_their_exclusive_parent() {
+        let mut world = FlowWorldBuilder::default();
+        world.uniform_flow(Vec3::Z * 3.0, wide());
+        let interior = world.region(Transform::default());
+        world
+            .world_mut()
+            .entity_mut(interior)
+            .insert(ExclusiveRegion);
+        world.uniform_flow_in(interior, Vec3::X * 3.0, wide());
+        nested(&mut world, interior, -0.3, 0.2, Vec3::Y * 3.0);
+        let sealed = nested(&mut world, interior, 0.3, 0.2, Vec3::NEG_Y * 3.0);
+        world.world_mut().entity_mut(sealed).insert(IsolatedRegion);
+        world.step(1);
+
+        assert_velocity(sample(&mut world, Vec3::ZERO).velocity(), Vec3::X * 3.0);
+        assert_velocity(
+            sample(&mut world, Vec3::X * -0.3).velocity(),
+            Vec3::new(1.5, 1.5, 0.0),
+        );
+        assert_velocity(
+            sample(&mut world, Vec3::X * 0.3).velocity(),
+            Vec3::NEG_Y * 3.0,
+        );
+    }
+}
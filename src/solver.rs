@@ -0,0 +1,504 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::prelude::{Component, IntoScheduleConfigs, Query, Res, ResMut};
+use bevy_math::{
+    BVec3, IVec3, Quat, UVec3, Vec3, Vec3A,
+    bounding::Aabb3d,
+    primitives::{Sphere, Triangle3d},
+};
+use bevy_mesh::Mesh;
+use bevy_time::Time;
+use bevy_transform::components::{GlobalTransform, Transform};
+
+use crate::{
+    field::FlowField,
+    flow::{AIR_DENSITY, Flow, FlowSystems, FlowVector},
+};
+
+/// Runs a simple incompressible fluid solver over a [`Flow`]'s field every frame, so that the
+/// flow evolves instead of staying a static bake.
+///
+/// Each step advects the velocity along itself, diffuses it by `viscosity`, and projects it to be
+/// divergence-free, following Stam's "Stable Fluids". The field's contents when the solver starts
+/// are used as the initial velocity, and the result is written back to the field with a uniform
+/// `density`. The solver runs on the CPU, so keep fields small.
+#[derive(Component, Clone, Debug)]
+pub struct SimulatedFlowField {
+    /// Kinematic viscosity in square meters per second.
+    pub viscosity: f32,
+    /// Jacobi iterations used for diffusion and pressure projection.
+    pub iterations: u32,
+    /// Density of the simulated medium in kg/m³.
+    pub density: f32,
+    /// What happens to the flow at each face of the grid. Open by default.
+    pub boundaries: SolverBoundaries,
+    size: UVec3,
+    velocity: Vec<Vec3>,
+}
+
+impl Default for SimulatedFlowField {
+    fn default() -> Self {
+        Self {
+            viscosity: 1.5e-5,
+            iterations: 20,
+            density: AIR_DENSITY,
+            boundaries: SolverBoundaries::default(),
+            size: UVec3::ZERO,
+            velocity: Vec::new(),
+        }
+    }
+}
+
+/// How a face of a [`SimulatedFlowField`]'s grid treats the flow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SolverBoundary {
+    /// Flow leaves and enters freely, as at the ends of a wind tunnel.
+    #[default]
+    Open,
+    /// A wall the flow sticks to, such as in a sealed room.
+    Closed,
+    /// Flow leaving through the face enters through the opposite one, so the field tiles. Only
+    /// takes effect when both faces of an axis are periodic, and is open otherwise.
+    Periodic,
+}
+
+/// The [`SolverBoundary`] of each face of a grid, indexed by axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SolverBoundaries {
+    /// The faces at the low end of the X, Y, and Z axes.
+    pub min: [SolverBoundary; 3],
+    /// The faces at the high end of the X, Y, and Z axes.
+    pub max: [SolverBoundary; 3],
+}
+
+impl SolverBoundaries {
+    /// The same boundary on every face.
+    pub fn uniform(boundary: SolverBoundary) -> Self {
+        Self {
+            min: [boundary; 3],
+            max: [boundary; 3],
+        }
+    }
+
+    /// Sets both faces of `axis` to `boundary`.
+    pub fn with_axis(mut self, axis: usize, boundary: SolverBoundary) -> Self {
+        self.min[axis] = boundary;
+        self.max[axis] = boundary;
+        self
+    }
+
+    fn periodic(&self) -> BVec3 {
+        let periodic = |axis: usize| {
+            self.min[axis] == SolverBoundary::Periodic && self.max[axis] == SolverBoundary::Periodic
+        };
+        BVec3::new(periodic(0), periodic(1), periodic(2))
+    }
+
+    /// Whether `texel` touches a closed face of a grid of `size`.
+    fn is_closed(&self, texel: UVec3, size: UVec3) -> bool {
+        (0..3).any(|axis| {
+            (texel[axis] == 0 && self.min[axis] == SolverBoundary::Closed)
+                || (texel[axis] == size[axis] - 1 && self.max[axis] == SolverBoundary::Closed)
+        })
+    }
+}
+
+impl SimulatedFlowField {
+    /// The current velocity grid, in the field's texel order.
+    pub fn velocity(&self) -> &[Vec3] {
+        &self.velocity
+    }
+
+    fn reset(&mut self, field: &FlowField) {
+        self.size = field.size();
+        let count = field.size().element_product() as usize;
+        self.velocity = (0..count)
+            .map(|index| field.get_index(index).velocity())
+            .collect();
+    }
+
+    /// Advances the simulation by `dt` seconds on a grid with the given world-space cell size,
+    /// whose axes are turned by `rotation` from the world's.
+    fn step(&mut self, dt: f32, cell: Vec3, rotation: Quat, constraints: &[CellConstraint]) {
+        let grid = Grid {
+            size: self.size,
+            periodic: self.boundaries.periodic(),
+        };
+        // Velocities are kept in world space like the field's, but solved along the grid's axes.
+        let to_grid = rotation.inverse();
+        for velocity in &mut self.velocity {
+            *velocity = to_grid * *velocity;
+        }
+
+        let previous = self.velocity.clone();
+        for (index, velocity) in self.velocity.iter_mut().enumerate() {
+            let origin = grid.texel(index).as_vec3() - previous[index] * dt / cell;
+            *velocity = grid.sample(&previous, origin);
+        }
+        self.constrain(grid, to_grid, constraints);
+
+        if self.viscosity > 0.0 {
+            let rate = self.viscosity * dt / (cell * cell);
+            let advected = self.velocity.clone();
+            let mut next = advected.clone();
+            for _ in 0..self.iterations {
+                for (index, value) in next.iter_mut().enumerate() {
+                    let texel = grid.texel(index);
+                    let mut sum = advected[index];
+                    for axis in 0..3 {
+                        let (below, above) = grid.neighbors(texel, axis);
+                        sum += (self.velocity[below] + self.velocity[above]) * rate[axis];
+                    }
+                    *value = sum / (1.0 + 2.0 * rate.element_sum());
+                }
+                core::mem::swap(&mut self.velocity, &mut next);
+            }
+        }
+
+        self.project(grid, cell);
+        self.constrain(grid, to_grid, constraints);
+
+        for velocity in &mut self.velocity {
+            *velocity = rotation * *velocity;
+        }
+    }
+
+    /// Applies `constraints`, whose world-space velocities are turned by `to_grid` first.
+    fn constrain(&mut self, grid: Grid, to_grid: Quat, constraints: &[CellConstraint]) {
+        for (index, (velocity, constraint)) in self.velocity.iter_mut().zip(constraints).enumerate()
+        {
+            if self.boundaries.is_closed(grid.texel(index), grid.size) {
+                *velocity = Vec3::ZERO;
+                continue;
+            }
+            match *constraint {
+                CellConstraint::Free => {}
+                CellConstraint::Source {
+                    velocity: target,
+                    blend,
+                } => *velocity = velocity.lerp(to_grid * target, blend),
+                CellConstraint::Solid => *velocity = Vec3::ZERO,
+            }
+        }
+    }
+
+    /// Removes the divergent part of the velocity by solving for pressure.
+    fn project(&mut self, grid: Grid, cell: Vec3) {
+        let divergence: Vec<f32> = (0..self.velocity.len())
+            .map(|index| {
+                let texel = grid.texel(index);
+                (0..3)
+                    .map(|axis| {
+                        let (below, above) = grid.neighbors(texel, axis);
+                        (self.velocity[above][axis] - self.velocity[below][axis])
+                            / (2.0 * cell[axis])
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let inverse_squared = 1.0 / (cell * cell);
+        let mut pressure = vec![0.0; self.velocity.len()];
+        let mut next = pressure.clone();
+        for _ in 0..self.iterations {
+            for (index, value) in next.iter_mut().enumerate() {
+                let texel = grid.texel(index);
+                let mut sum = -divergence[index];
+                for axis in 0..3 {
+                    let (below, above) = grid.neighbors(texel, axis);
+                    sum += (pressure[below] + pressure[above]) * inverse_squared[axis];
+                }
+                *value = sum / (2.0 * inverse_squared.element_sum());
+            }
+            core::mem::swap(&mut pressure, &mut next);
+        }
+
+        for (index, velocity) in self.velocity.iter_mut().enumerate() {
+            let texel = grid.texel(index);
+            for axis in 0..3 {
+                let (below, above) = grid.neighbors(texel, axis);
+                velocity[axis] -= (pressure[above] - pressure[below]) / (2.0 * cell[axis]);
+            }
+        }
+    }
+}
+
+/// Drives the velocity of a [`SimulatedFlowField`] inside the entity's [`SolverShape`], like a
+/// fan or vent.
+///
+/// Every frame the velocity of covered cells closes `1 - e^(-rate * dt)` of the distance to
+/// `velocity`.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(SolverShape)]
+pub struct FlowSource {
+    /// How quickly covered cells reach `velocity`, in inverse seconds.
+    pub rate: f32,
+    /// The world-space velocity to drive toward, in meters per second.
+    pub velocity: Vec3,
+}
+
+/// Blocks the flow of a [`SimulatedFlowField`] inside the entity's [`SolverShape`].
+///
+/// Covered cells are voxelized every frame and held at zero velocity, so obstacles may move.
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[require(SolverShape)]
+pub struct FlowObstacle;
+
+/// The region a [`FlowSource`] or [`FlowObstacle`] covers, in the entity's local space.
+///
+/// Defaults to a sphere of radius `0.5`, which fills the unit cube like a flow's field does.
+#[derive(Component, Clone, Debug)]
+#[require(Transform)]
+pub enum SolverShape {
+    Aabb(Aabb3d),
+    Sphere(Sphere),
+    /// A closed triangle mesh, tested with ray parity. Costs grow with the triangle count, so
+    /// prefer simple proxy meshes.
+    Mesh(Handle<Mesh>),
+}
+
+impl Default for SolverShape {
+    fn default() -> Self {
+        Self::Sphere(Sphere::new(0.5))
+    }
+}
+
+/// A [`SolverShape`] with its mesh data resolved.
+enum ResolvedShape {
+    Aabb(Aabb3d),
+    Sphere(Sphere),
+    Triangles(Vec<Triangle3d>),
+}
+
+impl ResolvedShape {
+    fn resolve(shape: &SolverShape, meshes: Option<&Assets<Mesh>>) -> Option<Self> {
+        Some(match shape {
+            SolverShape::Aabb(aabb) => Self::Aabb(*aabb),
+            SolverShape::Sphere(sphere) => Self::Sphere(*sphere),
+            SolverShape::Mesh(handle) => {
+                Self::Triangles(meshes?.get(handle)?.triangles().ok()?.collect())
+            }
+        })
+    }
+
+    fn contains(&self, local: Vec3) -> bool {
+        match self {
+            Self::Aabb(aabb) => {
+                let local = Vec3A::from(local);
+                local.cmpge(aabb.min).all() && local.cmple(aabb.max).all()
+            }
+            Self::Sphere(sphere) => local.length_squared() <= sphere.radius * sphere.radius,
+            Self::Triangles(triangles) => {
+                // Skewed so the ray is unlikely to graze shared edges exactly.
+                let direction = Vec3::new(1.0, 1e-3, 2e-3);
+                let crossings = triangles
+                    .iter()
+                    .filter(|triangle| ray_hits_triangle(local, direction, triangle))
+                    .count();
+                crossings % 2 == 1
+            }
+        }
+    }
+}
+
+/// Möller–Trumbore intersection of a ray with a triangle, counting only hits ahead of `origin`.
+fn ray_hits_triangle(origin: Vec3, direction: Vec3, triangle: &Triangle3d) -> bool {
+    let [a, b, c] = triangle.vertices;
+    let edge_1 = b - a;
+    let edge_2 = c - a;
+    let p = direction.cross(edge_2);
+    let determinant = edge_1.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return false;
+    }
+    let inverse = 1.0 / determinant;
+    let offset = origin - a;
+    let u = offset.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+    let q = offset.cross(edge_1);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+    edge_2.dot(q) * inverse > 0.0
+}
+
+/// What sources and obstacles impose on a single solver cell.
+#[derive(Clone, Copy)]
+enum CellConstraint {
+    Free,
+    Source { velocity: Vec3, blend: f32 },
+    Solid,
+}
+
+/// Index math for a dense grid in [`FlowField`] texel order.
+#[derive(Clone, Copy)]
+struct Grid {
+    size: UVec3,
+    /// The axes that wrap around instead of clamping.
+    periodic: BVec3,
+}
+
+impl Grid {
+    fn index(self, texel: UVec3) -> usize {
+        (texel.x + self.size.x * (texel.y + self.size.y * texel.z)) as usize
+    }
+
+    fn texel(self, index: usize) -> UVec3 {
+        let index = index as u32;
+        UVec3::new(
+            index % self.size.x,
+            index / self.size.x % self.size.y,
+            index / (self.size.x * self.size.y),
+        )
+    }
+
+    /// Wraps periodic axes of `texel` and clamps the others to the grid.
+    fn fit(self, texel: IVec3) -> UVec3 {
+        let size = self.size.as_ivec3();
+        IVec3::select(
+            self.periodic,
+            texel.rem_euclid(size),
+            texel.clamp(IVec3::ZERO, size - 1),
+        )
+        .as_uvec3()
+    }
+
+    /// The indices of the texels before and after `texel` along `axis`.
+    fn neighbors(self, texel: UVec3, axis: usize) -> (usize, usize) {
+        let mut offset = IVec3::ZERO;
+        offset[axis] = 1;
+        let below = self.fit(texel.as_ivec3() - offset);
+        let above = self.fit(texel.as_ivec3() + offset);
+        (self.index(below), self.index(above))
+    }
+
+    /// Trilinearly samples `values` at fractional texel coordinates.
+    fn sample(self, values: &[Vec3], coords: Vec3) -> Vec3 {
+        let size = self.size.as_vec3();
+        let coords = Vec3::select(
+            self.periodic,
+            coords.rem_euclid(size),
+            coords.clamp(Vec3::ZERO, size - 1.0),
+        );
+        let base = coords.floor();
+        let t = coords - base;
+        let base = self.fit(base.as_ivec3());
+        let next = self.fit(base.as_ivec3() + 1);
+
+        let at = |x: bool, y: bool, z: bool| {
+            values[self.index(UVec3::new(
+                if x { next.x } else { base.x },
+                if y { next.y } else { base.y },
+                if z { next.z } else { base.z },
+            ))]
+        };
+        let lerp_x = |y, z| at(false, y, z).lerp(at(true, y, z), t.x);
+        let lerp_y = |z| lerp_x(false, z).lerp(lerp_x(true, z), t.y);
+        lerp_y(false).lerp(lerp_y(true), t.z)
+    }
+}
+
+pub struct SolverPlugin;
+
+impl Plugin for SolverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, simulate_flow_fields.in_set(FlowSystems));
+    }
+}
+
+fn simulate_flow_fields(
+    time: Res<Time>,
+    mut fields: ResMut<Assets<FlowField>>,
+    meshes: Option<Res<Assets<Mesh>>>,
+    mut simulations: Query<(&Flow, &GlobalTransform, &mut SimulatedFlowField)>,
+    shapes: Query<(
+        &SolverShape,
+        &GlobalTransform,
+        Option<&FlowSource>,
+        Option<&FlowObstacle>,
+    )>,
+) {
+    trace_span!("vane::solve");
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+
+    // Obstacles go first so they win over sources where both cover a cell.
+    let mut shapes: Vec<_> = shapes
+        .iter()
+        .filter_map(|(shape, transform, source, obstacle)| {
+            let constraint = match (source, obstacle) {
+                (_, Some(_)) => CellConstraint::Solid,
+                (Some(source), None) => CellConstraint::Source {
+                    velocity: source.velocity,
+                    blend: 1.0 - (-source.rate * dt).exp(),
+                },
+                (None, None) => return None,
+            };
+            let shape = ResolvedShape::resolve(shape, meshes.as_deref())?;
+            Some((shape, transform.affine().inverse(), constraint))
+        })
+        .collect();
+    shapes.sort_by_key(|(.., constraint)| !matches!(constraint, CellConstraint::Solid));
+
+    for (flow, transform, mut simulation) in &mut simulations {
+        let Some(field) = fields.get_mut(&flow.field) else {
+            continue;
+        };
+        if simulation.size != field.size() {
+            simulation.reset(field);
+        }
+
+        let grid = Grid {
+            size: field.size(),
+            periodic: BVec3::FALSE,
+        };
+        let constraints: Vec<_> = (0..simulation.velocity.len())
+            .map(|index| {
+                let position = transform.transform_point(field.texel_center(grid.texel(index)));
+                shapes
+                    .iter()
+                    .find(|(shape, inverse, _)| shape.contains(inverse.transform_point3(position)))
+                    .map_or(CellConstraint::Free, |(.., constraint)| *constraint)
+            })
+            .collect();
+
+        let (scale, rotation, _) = transform.to_scale_rotation_translation();
+        let cell = scale.abs().max(Vec3::splat(f32::EPSILON)) / field.size().as_vec3();
+        simulation.step(dt, cell, rotation, &constraints);
+
+        let density = simulation.density;
+        for (texel, velocity) in field.data_mut().iter_mut().zip(&simulation.velocity) {
+            *texel = FlowVector::from_velocity(*velocity, density);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn advects_along_rotated_grid_axes() {
+        // Turned so the grid's Z axis runs along world X.
+        let rotation = Quat::from_rotation_y(FRAC_PI_2);
+        let mut simulation = SimulatedFlowField {
+            viscosity: 0.0,
+            iterations: 0,
+            size: UVec3::new(1, 1, 4),
+            velocity: vec![Vec3::ZERO, Vec3::X * 4.0, Vec3::X * 4.0, Vec3::X * 4.0],
+            ..Default::default()
+        };
+        simulation.step(0.25, Vec3::ONE, rotation, &[CellConstraint::Free; 4]);
+
+        // The still air upstream is carried one cell downstream, along world X.
+        assert!(simulation.velocity()[1].length() < 1e-4);
+        assert!(simulation.velocity()[2].distance(Vec3::X * 4.0) < 1e-4);
+    }
+}
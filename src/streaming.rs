@@ -0,0 +1,89 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{
+    prelude::{
+        Commands, Component, Entity, Event, IntoScheduleConfigs, OnAdd, OnRemove, Query, Res,
+        Trigger, With, Without,
+    },
+    relationship::RelationshipTarget,
+};
+
+use crate::{
+    field::FlowField,
+    flow::{Flow, FlowSystems},
+    region::{Contains, Region},
+};
+
+/// Triggered on a [`Region`] when it is spawned, such as when its chunk streams in.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RegionLoaded;
+
+/// Triggered on a [`Region`] when it is despawned, such as when its chunk streams out. By the
+/// time observers run, the region may no longer exist.
+///
+/// Despawning a region despawns the flows in it, which releases their fields unless something
+/// else holds them.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RegionUnloaded;
+
+/// Triggered on a [`Region`] once the fields of all its flows and its [`PrefetchFields`] are
+/// loaded.
+///
+/// This is triggered once per region, which is then marked [`RegionReady`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RegionFieldsReady;
+
+/// Fields a [`Region`] will need soon, such as those of flows spawned when it activates.
+///
+/// Holding the handles starts loading the fields ahead of time and keeps them alive for as long
+/// as the region is loaded.
+#[derive(Component, Clone, Debug, Default)]
+pub struct PrefetchFields(pub Vec<Handle<FlowField>>);
+
+/// Marks a [`Region`] whose fields have all been loaded.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct RegionReady;
+
+pub struct StreamingPlugin;
+
+impl Plugin for StreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(region_loaded)
+            .add_observer(region_unloaded)
+            .add_systems(PostUpdate, check_region_fields.before(FlowSystems));
+    }
+}
+
+fn region_loaded(trigger: Trigger<OnAdd, Region>, mut commands: Commands) {
+    commands.trigger_targets(RegionLoaded, trigger.target());
+}
+
+fn region_unloaded(trigger: Trigger<OnRemove, Region>, mut commands: Commands) {
+    commands.trigger_targets(RegionUnloaded, trigger.target());
+}
+
+fn check_region_fields(
+    regions: Query<
+        (Entity, Option<&Contains>, Option<&PrefetchFields>),
+        (With<Region>, Without<RegionReady>),
+    >,
+    flows: Query<&Flow>,
+    fields: Res<Assets<FlowField>>,
+    mut commands: Commands,
+) {
+    for (region, contains, prefetch) in &regions {
+        let flow_fields = contains
+            .into_iter()
+            .flat_map(|contains| contains.iter())
+            .filter_map(|flow| flows.get(flow).ok())
+            .map(|flow| &flow.field);
+        let prefetched = prefetch.into_iter().flat_map(|prefetch| &prefetch.0);
+        if flow_fields
+            .chain(prefetched)
+            .all(|field| fields.contains(field))
+        {
+            commands.entity(region).insert(RegionReady);
+            commands.trigger_targets(RegionFieldsReady, region);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Helpers for testing wind-dependent gameplay headlessly, enabled by the `test-utils` feature.
+//!
+//! [`FlowWorldBuilder`] sets up an app with fixed timesteps and spawns regions, flows, and vanes
+//! with uniform fields, so results are deterministic. For logic that only cares about what vanes
+//! read, [`MockFlow`] skips fields entirely and feeds vanes a scripted flow.
+
+use core::time::Duration;
+
+use bevy_app::{App, PluginGroup, Plugins, PostUpdate, TaskPoolPlugin};
+use bevy_asset::{AssetPlugin, Assets};
+use bevy_ecs::prelude::{Entity, IntoScheduleConfigs, Query, Res, Resource, World};
+use bevy_math::{UVec3, Vec3};
+use bevy_time::{Time, TimePlugin, TimeUpdateStrategy};
+use bevy_transform::{TransformPlugin, components::Transform};
+
+use crate::{
+    VanePlugins,
+    activity::ActivityPlugin,
+    field::FlowField,
+    flow::{AIR_DENSITY, Flow, FlowPlugin, FlowVector},
+    measure::{Measure, Measured},
+    region::{InRegion, Region},
+    vane::{Vane, VaneSamples, VaneSystems},
+};
+
+/// Replaces what every vane samples with a function of the sample's position and the elapsed
+/// time, in place of the composed flows.
+///
+/// Installed by [`FlowWorldBuilder::mock_flow`]. The mock overwrites [`VaneSamples`] after vanes
+/// have sampled, so it bypasses sampling itself: [`VaneAccumulation`] and [`VaneSubframes`] see
+/// the composed flows rather than the mock, [`VaneSettings::change_epsilon`] doesn't hold back
+/// small changes, and gradients are cleared rather than taken from the mock. Test those with real
+/// flows instead.
+///
+/// [`VaneAccumulation`]: crate::vane::VaneAccumulation
+/// [`VaneSubframes`]: crate::vane::VaneSubframes
+/// [`VaneSettings::change_epsilon`]: crate::vane::VaneSettings::change_epsilon
+#[derive(Resource)]
+pub struct MockFlow(pub Box<dyn Fn(Vec3, f32) -> FlowVector + Send + Sync>);
+
+/// Sets up a headless [`App`] with the [`VanePlugins`] that advances by a fixed timestep on every
+/// [`step`](Self::step).
+pub struct FlowWorldBuilder {
+    app: App,
+}
+
+impl Default for FlowWorldBuilder {
+    fn default() -> Self {
+        Self::new(Duration::from_secs_f32(1.0 / 60.0))
+    }
+}
+
+impl FlowWorldBuilder {
+    pub fn new(timestep: Duration) -> Self {
+        Self::with_plugins(timestep, VanePlugins)
+    }
+
+    /// Like [`new`](Self::new), with flows and activity stepping in [`FixedUpdate`], as set up
+    /// by [`FlowPlugin::fixed`].
+    ///
+    /// [`FixedUpdate`]: bevy_app::FixedUpdate
+    pub fn fixed(timestep: Duration) -> Self {
+        Self::with_plugins(
+            timestep,
+            VanePlugins
+                .set(FlowPlugin::fixed())
+                .set(ActivityPlugin::fixed()),
+        )
+    }
+
+    fn with_plugins<M>(timestep: Duration, plugins: impl Plugins<M>) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            TimePlugin,
+            AssetPlugin::default(),
+            TransformPlugin,
+            plugins,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+        .add_systems(
+            PostUpdate,
+            apply_mock_flow
+                .in_set(VaneSystems::Sample)
+                .after(crate::vane::sample_vanes),
+        );
+        Self { app }
+    }
+
+    /// Feeds every vane `flow` instead of sampling. See [`MockFlow`] for what this skips.
+    pub fn mock_flow(
+        &mut self,
+        flow: impl Fn(Vec3, f32) -> FlowVector + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.app.insert_resource(MockFlow(Box::new(flow)));
+        self
+    }
+
+    /// Spawns a [`Region`] with `transform`.
+    pub fn region(&mut self, transform: Transform) -> Entity {
+        self.app.world_mut().spawn((Region, transform)).id()
+    }
+
+    /// Spawns a flow of air moving uniformly at `velocity`.
+    pub fn uniform_flow(&mut self, velocity: Vec3, transform: Transform) -> Entity {
+        self.flow(
+            FlowField::from_fn(UVec3::ONE, |_| {
+                FlowVector::from_velocity(velocity, AIR_DENSITY)
+            }),
+            transform,
+        )
+    }
+
+    /// Spawns a flow of air moving uniformly at `velocity` inside `region`.
+    pub fn uniform_flow_in(
+        &mut self,
+        region: Entity,
+        velocity: Vec3,
+        transform: Transform,
+    ) -> Entity {
+        let flow = self.uniform_flow(velocity, transform);
+        self.app
+            .world_mut()
+            .entity_mut(flow)
+            .insert(InRegion(region));
+        flow
+    }
+
+    /// Spawns a flow with the given field.
+    pub fn flow(&mut self, field: FlowField, transform: Transform) -> Entity {
+        let world = self.app.world_mut();
+        let field = world.resource_mut::<Assets<FlowField>>().add(field);
+        world.spawn((Flow::new(field), transform)).id()
+    }
+
+    /// Spawns a [`Vane`] at `position`.
+    pub fn vane(&mut self, position: Vec3) -> Entity {
+        let transform = Transform::from_translation(position);
+        self.app.world_mut().spawn((Vane, transform)).id()
+    }
+
+    /// Runs `frames` updates.
+    pub fn step(&mut self, frames: u32) -> &mut Self {
+        for _ in 0..frames {
+            self.app.update();
+        }
+        self
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    pub fn build(self) -> App {
+        self.app
+    }
+}
+
+fn apply_mock_flow(
+    time: Res<Time>,
+    mock: Option<Res<MockFlow>>,
+    mut vanes: Query<&mut VaneSamples>,
+) {
+    let Some(mock) = mock else {
+        return;
+    };
+    let elapsed = time.elapsed_secs();
+    for mut samples in &mut vanes {
+        for sample in &mut samples.0 {
+            sample.flow = mock.0(sample.position, elapsed);
+            sample.jacobian = None;
+        }
+    }
+}
+
+/// The latest result of the measure `M` on `entity`.
+///
+/// # Panics
+///
+/// Panics if the entity has no result for `M` yet.
+#[track_caller]
+pub fn measured<M: Measure>(world: &World, entity: Entity) -> M::Output {
+    match world.get::<Measured<M>>(entity) {
+        Some(measured) => measured.0.clone(),
+        None => panic!("{entity} has no {} result", core::any::type_name::<M>()),
+    }
+}
+
+/// Asserts that the scalar measure `M` on `entity` is within `tolerance` of `expected`.
+#[track_caller]
+pub fn assert_measured_near<M: Measure<Output = f32>>(
+    world: &World,
+    entity: Entity,
+    expected: f32,
+    tolerance: f32,
+) {
+    let actual = measured::<M>(world, entity);
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} on {entity} is {actual}, expected {expected} ± {tolerance}",
+        core::any::type_name::<M>()
+    );
+}
+
+/// Asserts that the vector measure `M` on `entity` is within `tolerance` of `expected`.
+#[track_caller]
+pub fn assert_measured_vec_near<M: Measure<Output = Vec3>>(
+    world: &World,
+    entity: Entity,
+    expected: Vec3,
+    tolerance: f32,
+) {
+    let actual = measured::<M>(world, entity);
+    assert!(
+        actual.distance(expected) <= tolerance,
+        "{} on {entity} is {actual}, expected {expected} ± {tolerance}",
+        core::any::type_name::<M>()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_transform::components::Transform;
+
+    use super::*;
+    use crate::{
+        flow::InheritedVelocity,
+        measure::{WindSpeed, WindVelocity},
+        vane::VaneGradient,
+    };
+
+    #[test]
+    fn mock_flows_replace_samples() {
+        let mut world = FlowWorldBuilder::default();
+        world.mock_flow(|position, _| FlowVector::from_velocity(position, AIR_DENSITY));
+        world.uniform_flow(Vec3::X * 3.0, Transform::from_scale(Vec3::splat(100.0)));
+        let vane = world.vane(Vec3::Y * 2.0);
+        world
+            .world_mut()
+            .entity_mut(vane)
+            .insert((WindVelocity, VaneGradient::default()));
+        world.step(2);
+
+        assert_measured_vec_near::<WindVelocity>(world.world(), vane, Vec3::Y * 2.0, 1e-4);
+        let samples = world.world().get::<VaneSamples>(vane).unwrap();
+        assert!(samples.0.iter().all(|sample| sample.jacobian.is_none()));
+    }
+
+    #[test]
+    fn fixed_worlds_step_flows() {
+        let mut world = FlowWorldBuilder::fixed(Duration::from_secs_f32(1.0 / 60.0));
+        let flow = world.uniform_flow(Vec3::X * 2.0, Transform::from_scale(Vec3::splat(100.0)));
+        let vane = world.vane(Vec3::ZERO);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+        for _ in 0..30 {
+            world
+                .world_mut()
+                .get_mut::<Transform>(flow)
+                .unwrap()
+                .translation
+                .y += 0.1;
+            world.step(1);
+        }
+
+        // The flow moves 6 m/s, inherited in the fixed steps that ran.
+        let velocity = world.world().get::<InheritedVelocity>(flow).unwrap().linear;
+        assert!(
+            (velocity.y - 6.0).abs() < 0.5,
+            "the flow moved at {velocity}"
+        );
+        assert!(measured::<WindVelocity>(world.world(), vane).x > 1.0);
+    }
+
+    #[test]
+    fn region_flows_stay_in_their_region() {
+        let mut world = FlowWorldBuilder::default();
+        let region = world.region(Transform::from_scale(Vec3::splat(4.0)));
+        world.uniform_flow_in(
+            region,
+            Vec3::X * 3.0,
+            Transform::from_scale(Vec3::splat(100.0)),
+        );
+        let inside = world.vane(Vec3::X);
+        let outside = world.vane(Vec3::X * 3.0);
+        for vane in [inside, outside] {
+            world.world_mut().entity_mut(vane).insert(WindSpeed);
+        }
+        world.step(2);
+
+        assert_measured_near::<WindSpeed>(world.world(), inside, 3.0, 1e-4);
+        assert_measured_near::<WindSpeed>(world.world(), outside, 0.0, 1e-4);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 1 ± 0.1")]
+    fn measured_assertions_report_misses() {
+        let mut world = FlowWorldBuilder::default();
+        let vane = world.vane(Vec3::ZERO);
+        world.world_mut().entity_mut(vane).insert(WindSpeed);
+        world.step(1);
+        assert_measured_near::<WindSpeed>(world.world(), vane, 1.0, 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "has no")]
+    fn measured_panics_without_a_result() {
+        let mut world = FlowWorldBuilder::default();
+        let vane = world.vane(Vec3::ZERO);
+        measured::<WindSpeed>(world.world(), vane);
+    }
+}
@@ -0,0 +1,61 @@
+use bevy_ecs::prelude::Resource;
+
+/// The size of the app's units of length, time, and mass, for games that don't use meters,
+/// seconds, and kilograms.
+///
+/// Quantities documented in SI units on the crate's components, such as
+/// [`FlowDriven::mass`](crate::drive::FlowDriven::mass) or
+/// [`AeroSurface::area`](crate::aero::AeroSurface), are read in these units instead, and forces and
+/// accelerations come out in them. Flow velocities are in length units per time unit, while
+/// densities stay in kg/m³ as authored, such as [`AIR_DENSITY`], and are converted when composing
+/// forces. Speeds the crate picks for you, from the [`WeatherWind`] and
+/// [`GustSettings`](crate::gust::GustSettings), are given in m/s and converted with
+/// [`speed`](Self::speed). [`beaufort_speed`] itself stays in m/s, and the
+/// [`DynamicPressure`](crate::measure::DynamicPressure) measure is only in pascals with SI units.
+///
+/// [`AIR_DENSITY`]: crate::flow::AIR_DENSITY
+/// [`beaufort_speed`]: crate::flow::beaufort_speed
+/// [`WeatherWind`]: crate::weather::WeatherWind
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct VaneUnits {
+    /// Meters per unit of length, such as `0.01` when 1 unit is 1 cm.
+    pub length: f32,
+    /// Seconds per unit of time.
+    pub time: f32,
+    /// Kilograms per unit of mass.
+    pub mass: f32,
+}
+
+impl Default for VaneUnits {
+    fn default() -> Self {
+        Self::SI
+    }
+}
+
+impl VaneUnits {
+    pub const SI: Self = Self {
+        length: 1.0,
+        time: 1.0,
+        mass: 1.0,
+    };
+
+    /// Units of `length` meters, with seconds and kilograms.
+    pub fn with_length(length: f32) -> Self {
+        Self { length, ..Self::SI }
+    }
+
+    /// Converts a density in kg/m³ to mass units per cubic length unit.
+    pub fn density(&self, density: f32) -> f32 {
+        density * self.length.powi(3) / self.mass
+    }
+
+    /// Converts a speed in m/s to length units per time unit.
+    pub fn speed(&self, speed: f32) -> f32 {
+        speed * self.time / self.length
+    }
+
+    /// Converts an acceleration in m/s² to length units per time unit squared.
+    pub fn acceleration(&self, acceleration: f32) -> f32 {
+        acceleration * self.time * self.time / self.length
+    }
+}
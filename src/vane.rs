@@ -0,0 +1,588 @@
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::{
+    Changed, Component, DetectChangesMut, Entity, IntoScheduleConfigs, Local, Query,
+    ReflectComponent, Res, ResMut, Resource, SystemSet, Trigger, With,
+};
+use bevy_math::{Mat3, Vec3};
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+use bevy_time::Time;
+use bevy_transform::{
+    TransformSystem,
+    components::{GlobalTransform, Transform},
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use smallvec::SmallVec;
+
+use crate::{
+    activity::{ActivityData, ActivityTier, is_inactive},
+    bounds::{VaneAabb, update_vane_aabbs},
+    flow::{FlowLayers, FlowSystems, FlowVector},
+    region::OriginRebased,
+    sampler::FlowSampler,
+    visibility::{VisibilityData, is_hidden},
+};
+
+/// A sensor that samples the composed flow at its position every frame.
+///
+/// The results are written to the vane's [`VaneSamples`] in [`PostUpdate`], so systems reading
+/// them earlier in the frame see the previous frame's flow. Vanes set to `Visibility::Hidden`
+/// report no samples, as do [`SpatialActivity`](crate::activity::SpatialActivity) vanes outside
+/// every active region, and disabled vanes are not updated.
+///
+/// A vane only sees flows sharing at least one of its [`FlowLayers`], which default to all of
+/// them. Give a water-current vane only the water layer to keep it from reporting air gusts.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+#[require(Transform, FlowLayers = FlowLayers::all(), VaneSamples, VaneAabb)]
+pub struct Vane;
+
+/// A single flow sample taken by a [`Vane`].
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub struct VaneSample {
+    /// The world-space position the sample was taken at.
+    pub position: Vec3,
+    pub flow: FlowVector,
+    /// The velocity gradient at the sample, with column `i` holding the change in velocity per
+    /// meter along world axis `i`. Only taken for vanes with a [`VaneGradient`].
+    pub jacobian: Option<Mat3>,
+}
+
+impl VaneSample {
+    /// The curl of the velocity, in radians per second about each axis, if the gradient was taken.
+    pub fn vorticity(&self) -> Option<Vec3> {
+        let j = self.jacobian?;
+        Some(Vec3::new(
+            j.y_axis.z - j.z_axis.y,
+            j.z_axis.x - j.x_axis.z,
+            j.x_axis.y - j.y_axis.x,
+        ))
+    }
+
+    /// The divergence of the velocity in inverse seconds, if the gradient was taken.
+    pub fn divergence(&self) -> Option<f32> {
+        let j = self.jacobian?;
+        Some(j.x_axis.x + j.y_axis.y + j.z_axis.z)
+    }
+}
+
+/// The samples taken by a [`Vane`] during the last update.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct VaneSamples(pub Vec<VaneSample>);
+
+impl VaneSamples {
+    /// The average flow over all samples, or zero if there are none.
+    pub fn mean(&self) -> FlowVector {
+        if self.0.is_empty() {
+            return FlowVector::ZERO;
+        }
+        let sum = self
+            .0
+            .iter()
+            .fold(FlowVector::ZERO, |sum, sample| sum + sample.flow);
+        sum * (1.0 / self.0.len() as f32)
+    }
+}
+
+/// The time in seconds since a [`Vane`]'s [`VaneSamples`] were last taken, for spotting vanes
+/// whose readings lag behind the flow.
+///
+/// Vanes in lower [`ActivityTier`]s keep their samples between the frames they sample on, which
+/// this reveals. It starts out infinite and is only tracked on vanes that have it.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct VaneSampleAge(pub(crate) f32);
+
+impl VaneSampleAge {
+    pub fn secs(&self) -> f32 {
+        self.0
+    }
+}
+
+impl Default for VaneSampleAge {
+    fn default() -> Self {
+        Self(f32::INFINITY)
+    }
+}
+
+/// Makes a [`Vane`] also sample each group of `layers` on its own, at the same positions as its
+/// [`VaneSamples`], so one vane can tell water currents from air wind.
+///
+/// Each group is intersected with the vane's own [`FlowLayers`]. Per-layer samples carry no
+/// [`jacobian`](VaneSample::jacobian). Read them directly or through a
+/// [`PerLayer`](crate::measure::PerLayer) measure.
+#[derive(Component, Clone, Debug, Default)]
+#[require(Vane)]
+pub struct LayerSamples {
+    pub layers: SmallVec<[FlowLayers; 2]>,
+    samples: Vec<VaneSamples>,
+}
+
+impl LayerSamples {
+    pub fn new(layers: impl IntoIterator<Item = FlowLayers>) -> Self {
+        Self {
+            layers: layers.into_iter().collect(),
+            samples: Vec::new(),
+        }
+    }
+
+    /// The samples taken for the group `layers`, if it is one of the groups.
+    pub fn get(&self, layers: FlowLayers) -> Option<&VaneSamples> {
+        let index = self.layers.iter().position(|&group| group == layers)?;
+        self.samples.get(index)
+    }
+
+    /// Iterates over each group with its samples from the last update.
+    pub fn iter(&self) -> impl Iterator<Item = (FlowLayers, &VaneSamples)> {
+        self.layers.iter().copied().zip(&self.samples)
+    }
+}
+
+/// Extra points a [`Vane`] samples besides its origin, in its local space, such as the wingtips,
+/// nose, and tail of an aircraft.
+///
+/// Samples are reported in [`VaneSamples`] after the origin's, in this order.
+#[derive(Component, Clone, Debug, Default)]
+#[require(Vane)]
+pub struct VaneOffsets(pub SmallVec<[Vec3; 4]>);
+
+/// Makes a [`Vane`] also take the velocity gradient at each of its samples, filling
+/// [`VaneSample::jacobian`] so vorticity and shear can be read from a single vane.
+///
+/// The gradient is taken by central differences, `step` meters along each world axis, which costs
+/// six extra flow samples per sample.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component, Default)]
+#[require(Vane)]
+pub struct VaneGradient {
+    pub step: f32,
+}
+
+impl Default for VaneGradient {
+    fn default() -> Self {
+        Self { step: 0.1 }
+    }
+}
+
+/// Makes a [`Vane`] sample a box around itself instead of a single point, at `count` random
+/// positions that change every frame. With [`VaneOffsets`], each offset gets its own box.
+///
+/// Few samples per frame give noisy readings; add [`VaneAccumulation`] to average them over
+/// several frames.
+#[derive(Component, Clone, Debug)]
+#[require(Vane)]
+pub struct VaneJitter {
+    /// The size of the sampled box in the vane's local space.
+    pub extent: Vec3,
+    pub count: u32,
+    rng: ChaCha8Rng,
+}
+
+impl VaneJitter {
+    pub fn new(extent: Vec3, count: u32) -> Self {
+        Self {
+            extent,
+            count,
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+
+    /// Reseeds the jitter, such as to decorrelate vanes spawned with the same settings.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self
+    }
+
+    fn offset(&mut self) -> Vec3 {
+        let unit = Vec3::new(self.rng.random(), self.rng.random(), self.rng.random());
+        (unit - 0.5) * self.extent
+    }
+}
+
+/// Blends each frame's mean sample into a running average, so a [`VaneJitter`] with few samples
+/// per frame converges on a smooth reading over a few frames.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(Vane)]
+pub struct VaneAccumulation {
+    /// How much of each new frame's mean enters the average, from `0` to `1`.
+    pub blend: f32,
+    mean: Option<FlowVector>,
+}
+
+impl VaneAccumulation {
+    pub fn new(blend: f32) -> Self {
+        Self { blend, mean: None }
+    }
+
+    /// The accumulated mean flow, or zero before the vane's first sample.
+    pub fn mean(&self) -> FlowVector {
+        self.mean.unwrap_or(FlowVector::ZERO)
+    }
+
+    /// Forgets the average, such as after the vane teleports.
+    pub fn reset(&mut self) {
+        self.mean = None;
+    }
+
+    fn accumulate(&mut self, sample: FlowVector) {
+        let mean = self
+            .mean
+            .map_or(sample, |mean| mean.lerp(sample, self.blend));
+        self.mean = Some(mean);
+    }
+}
+
+/// Makes a fast-moving [`Vane`] sample at `steps` points spread along the path it moved since the
+/// previous frame, so readings don't strobe as it passes through small flows.
+///
+/// Combined with [`VaneJitter`], each step takes the jittered samples, multiplying the cost.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(Vane)]
+pub struct VaneSubframes {
+    pub steps: u32,
+    previous: Option<Vec3>,
+}
+
+impl VaneSubframes {
+    pub fn new(steps: u32) -> Self {
+        Self {
+            steps,
+            previous: None,
+        }
+    }
+
+    /// Forgets the previous position, such as after the vane teleports, so the next frame doesn't
+    /// sample along the jump.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Shifts the previous position along with a rebased world origin.
+    fn rebase(&mut self, offset: Vec3) {
+        if let Some(previous) = &mut self.previous {
+            *previous += offset;
+        }
+    }
+}
+
+/// Global settings for sampling vanes.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct VaneSettings {
+    /// How much a sample's position, momentum, or density must move between frames for the
+    /// vane's [`VaneSamples`] to be marked changed. Systems filtering on `Changed<VaneSamples>`
+    /// then skip vanes sitting in steady flow.
+    pub change_epsilon: f32,
+    /// Frames between samples for vanes in the [`ActivityTier::Reduced`] tier, which keep their
+    /// previous samples in between.
+    pub reduced_interval: u32,
+    /// Like [`reduced_interval`](Self::reduced_interval), for [`ActivityTier::Dormant`] vanes.
+    pub dormant_interval: u32,
+}
+
+impl Default for VaneSettings {
+    fn default() -> Self {
+        Self {
+            change_epsilon: 0.0,
+            reduced_interval: 4,
+            dormant_interval: 16,
+        }
+    }
+}
+
+impl VaneSettings {
+    fn interval(&self, tier: ActivityTier) -> u32 {
+        match tier {
+            ActivityTier::Active => 1,
+            ActivityTier::Reduced => self.reduced_interval,
+            ActivityTier::Dormant => self.dormant_interval,
+        }
+        .max(1)
+    }
+}
+
+/// The vanes whose [`VaneSamples`] changed during the latest frame's sampling, for systems that
+/// process results in bulk.
+///
+/// Refilled every frame in [`VaneSystems::Measure`], replacing entries no one drained. Like
+/// `Changed<VaneSamples>`, steady vanes are left out per [`VaneSettings::change_epsilon`].
+#[derive(Resource, Clone, Debug, Default)]
+pub struct VaneUpdates(Vec<Entity>);
+
+impl VaneUpdates {
+    /// Takes the updated vanes, leaving none until the next frame.
+    pub fn drain(&mut self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.drain(..)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The stages of a frame of flow simulation, for ordering your own systems around.
+///
+/// In [`PostUpdate`], the stages run in the order [`Activity`](Self::Activity), [`FlowSystems`],
+/// [`Prepare`](Self::Prepare), [`Sample`](Self::Sample), and [`Measure`](Self::Measure), all after
+/// transform propagation. [`Respond`](Self::Respond) runs in
+/// [`Update`](bevy_app::Update) and sees the samples taken during the previous frame.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VaneSystems {
+    /// Reports flows and vanes starting and stopping taking part in sampling. Runs in the
+    /// [`ActivityPlugin`](crate::activity::ActivityPlugin)'s schedule, in the stages of
+    /// [`ActivitySystems`](crate::activity::ActivitySystems).
+    Activity,
+    /// Updates vane state needed for sampling, such as their bounds.
+    Prepare,
+    /// Fills [`VaneSamples`] for every vane and registered point set.
+    Sample,
+    /// Turns samples into [`Measured`](crate::measure::Measured) values.
+    Measure,
+    /// Turns samples into forces and motion, such as drag and sail forces.
+    Respond,
+}
+
+pub struct VanePlugin;
+
+impl Plugin for VanePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VaneSettings>()
+            .init_resource::<VaneUpdates>()
+            .register_type::<Vane>()
+            .register_type::<VaneSamples>()
+            .register_type::<VaneGradient>()
+            .register_type::<VaneSampleAge>()
+            .configure_sets(
+                PostUpdate,
+                (
+                    VaneSystems::Prepare,
+                    VaneSystems::Sample,
+                    VaneSystems::Measure,
+                )
+                    .chain()
+                    .after(TransformSystem::TransformPropagate)
+                    .after(FlowSystems),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    update_vane_aabbs.in_set(VaneSystems::Prepare),
+                    sample_vanes.in_set(VaneSystems::Sample),
+                    collect_vane_updates.in_set(VaneSystems::Measure),
+                ),
+            )
+            .add_observer(rebase_subframes);
+    }
+}
+
+fn rebase_subframes(trigger: Trigger<OriginRebased>, mut subframes: Query<&mut VaneSubframes>) {
+    for mut subframes in &mut subframes {
+        subframes.rebase(trigger.offset);
+    }
+}
+
+pub(crate) fn sample_vanes(
+    mut frame: Local<u32>,
+    sampler: FlowSampler,
+    settings: Res<VaneSettings>,
+    time: Res<Time>,
+    mut vanes: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &FlowLayers,
+            VisibilityData,
+            ActivityData,
+            Option<&ActivityTier>,
+            &mut VaneSamples,
+            Option<&mut VaneJitter>,
+            Option<&mut VaneAccumulation>,
+            Option<&mut VaneSubframes>,
+            Option<&VaneOffsets>,
+            Option<&VaneGradient>,
+            Option<&mut LayerSamples>,
+            Option<&mut VaneSampleAge>,
+        ),
+        With<Vane>,
+    >,
+) {
+    trace_span!("vane::sample");
+    *frame = frame.wrapping_add(1);
+    let frame = *frame;
+    // Vanes are independent, so large counts are spread over the compute task pool.
+    vanes.par_iter_mut().for_each(
+        |(
+            entity,
+            transform,
+            layers,
+            visibility,
+            activity,
+            tier,
+            mut samples,
+            mut jitter,
+            accumulation,
+            subframes,
+            offsets,
+            gradient,
+            layer_samples,
+            age,
+        )| {
+            let sampling = !is_hidden(visibility) && !is_inactive(activity);
+            // Lower tiers are staggered so their samples spread over frames.
+            let interval = settings.interval(tier.copied().unwrap_or_default());
+            let skipped = entity.index().wrapping_add(frame) % interval != 0;
+            if let Some(mut age) = age {
+                age.0 = if sampling && !skipped {
+                    0.0
+                } else {
+                    age.0 + time.delta_secs()
+                };
+            }
+            if sampling && skipped {
+                return;
+            }
+            // Written without change detection, so vanes in steady flow don't look changed.
+            let mut writer = SampleWriter {
+                samples: &mut samples.bypass_change_detection().0,
+                len: 0,
+                changed: false,
+                epsilon: settings.change_epsilon,
+            };
+            let translation = transform.translation();
+            let (steps, previous) = match subframes {
+                Some(mut subframes) => (
+                    subframes.steps.max(1),
+                    subframes.previous.replace(translation),
+                ),
+                None => (1, None),
+            };
+            if sampling {
+                for step in 0..steps {
+                    // Steps end on the current position, which a single step samples alone.
+                    let t = (step + 1) as f32 / steps as f32;
+                    let shift = previous.map_or(Vec3::ZERO, |previous| {
+                        previous.lerp(translation, t) - translation
+                    });
+                    let mut sample = |position: Vec3| {
+                        let position = position + shift;
+                        let jacobian = gradient.map(|gradient| {
+                            let step = gradient.step.max(f32::EPSILON);
+                            let derivative = |axis: Vec3| {
+                                let ahead = sampler.sample(position + axis * step, *layers);
+                                let behind = sampler.sample(position - axis * step, *layers);
+                                (ahead.velocity() - behind.velocity()) / (2.0 * step)
+                            };
+                            Mat3::from_cols(
+                                derivative(Vec3::X),
+                                derivative(Vec3::Y),
+                                derivative(Vec3::Z),
+                            )
+                        });
+                        writer.push(VaneSample {
+                            position,
+                            flow: sampler.sample(position, *layers),
+                            jacobian,
+                        });
+                    };
+                    let offsets = offsets.iter().flat_map(|offsets| &offsets.0);
+                    for &local in [Vec3::ZERO].iter().chain(offsets) {
+                        match &mut jitter {
+                            Some(jitter) => {
+                                for _ in 0..jitter.count {
+                                    sample(transform.transform_point(local + jitter.offset()));
+                                }
+                            }
+                            None => sample(transform.transform_point(local)),
+                        }
+                    }
+                }
+            }
+            if writer.finish() {
+                samples.set_changed();
+            }
+            if let Some(mut layer_samples) = layer_samples {
+                let LayerSamples {
+                    layers: groups,
+                    samples: grouped,
+                } = &mut *layer_samples;
+                grouped.resize_with(groups.len(), VaneSamples::default);
+                for (group, grouped) in groups.iter().zip(grouped) {
+                    let mask = layers.intersection(*group);
+                    grouped.0.clear();
+                    grouped.0.extend(samples.0.iter().map(|sample| VaneSample {
+                        position: sample.position,
+                        flow: sampler.sample(sample.position, mask),
+                        jacobian: None,
+                    }));
+                }
+            }
+            if sampling && let Some(mut accumulation) = accumulation {
+                accumulation.accumulate(samples.mean());
+            }
+        },
+    );
+}
+
+fn collect_vane_updates(
+    mut updates: ResMut<VaneUpdates>,
+    vanes: Query<Entity, (With<Vane>, Changed<VaneSamples>)>,
+) {
+    updates.0.clear();
+    updates.0.extend(&vanes);
+}
+
+/// Overwrites a vane's samples in place, noting whether any moved by more than `epsilon`.
+struct SampleWriter<'a> {
+    samples: &'a mut Vec<VaneSample>,
+    len: usize,
+    changed: bool,
+    epsilon: f32,
+}
+
+impl SampleWriter<'_> {
+    fn push(&mut self, sample: VaneSample) {
+        match self.samples.get_mut(self.len) {
+            Some(old) => {
+                self.changed |= differs(old, &sample, self.epsilon);
+                *old = sample;
+            }
+            None => {
+                self.changed = true;
+                self.samples.push(sample);
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Drops leftover samples from the previous frame and returns whether anything changed.
+    fn finish(self) -> bool {
+        let changed = self.changed || self.samples.len() != self.len;
+        self.samples.truncate(self.len);
+        changed
+    }
+}
+
+fn differs(a: &VaneSample, b: &VaneSample, epsilon: f32) -> bool {
+    let momentum = (a.flow.momentum - b.flow.momentum).abs().max_element();
+    let density = (a.flow.density - b.flow.density).abs();
+    let position = (a.position - b.position).abs().max_element();
+    let jacobian = match (a.jacobian, b.jacobian) {
+        (Some(a), Some(b)) => (a - b)
+            .abs()
+            .to_cols_array()
+            .into_iter()
+            .fold(0.0, f32::max),
+        (None, None) => 0.0,
+        _ => f32::INFINITY,
+    };
+    momentum.max(density).max(position).max(jacobian) > epsilon
+}
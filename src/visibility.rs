@@ -0,0 +1,21 @@
+//! Visibility checks that compile away without the `render` feature, where nothing is hidden.
+
+#[cfg(feature = "render")]
+pub(crate) use bevy_render::view::Visibility;
+
+/// Query data for an entity's visibility, read with [`is_hidden`].
+#[cfg(feature = "render")]
+pub(crate) type VisibilityData = Option<&'static Visibility>;
+/// Stands in for the visibility when nothing can be hidden. The entity is free to fetch.
+#[cfg(not(feature = "render"))]
+pub(crate) type VisibilityData = bevy_ecs::entity::Entity;
+
+#[cfg(feature = "render")]
+pub(crate) fn is_hidden(visibility: Option<&Visibility>) -> bool {
+    visibility == Some(&Visibility::Hidden)
+}
+
+#[cfg(not(feature = "render"))]
+pub(crate) fn is_hidden(_: bevy_ecs::entity::Entity) -> bool {
+    false
+}
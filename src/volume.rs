@@ -0,0 +1,255 @@
+use bevy_ecs::prelude::{Component, ReflectComponent};
+use bevy_math::{Affine3A, Vec3, Vec3A, Vec3Swizzles, bounding::Aabb3d};
+use bevy_reflect::{Reflect, std_traits::ReflectDefault};
+
+use crate::bounds::transform_aabb;
+
+/// The region a [`Flow`](crate::flow::Flow) occupies, inscribed in its unit cube
+/// `[-0.5, 0.5]³`.
+///
+/// Points outside the volume are unaffected by the flow, and [`FlowVolume::world_aabb`] bounds
+/// the volume rather than the whole cube, so round effects like vortices don't waste the cube's
+/// corners. Flows without this component fill the whole cube.
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Component, Default)]
+pub enum FlowVolume {
+    #[default]
+    Box,
+    /// The sphere of radius `0.5`.
+    Sphere,
+    /// The cylinder of radius `0.5` and height `1` along the local `Y` axis.
+    Cylinder,
+    ConvexHull(ConvexHull),
+}
+
+impl FlowVolume {
+    /// Whether `local` lies inside the volume.
+    pub fn contains(&self, local: Vec3) -> bool {
+        match self {
+            Self::Box => local.abs().max_element() <= 0.5,
+            Self::Sphere => local.length_squared() <= 0.25,
+            Self::Cylinder => local.xz().length_squared() <= 0.25 && local.y.abs() <= 0.5,
+            Self::ConvexHull(hull) => hull.contains(local),
+        }
+    }
+
+    /// The local-space bounds of the volume.
+    pub fn local_aabb(&self) -> Aabb3d {
+        match self {
+            Self::Box | Self::Sphere | Self::Cylinder => Aabb3d::new(Vec3::ZERO, Vec3::splat(0.5)),
+            Self::ConvexHull(hull) => hull.aabb(),
+        }
+    }
+
+    /// The world-space bounds of the volume placed by `affine`.
+    ///
+    /// Unlike transforming [`local_aabb`](Self::local_aabb), spheres and cylinders are bounded
+    /// tightly however they are rotated.
+    pub fn world_aabb(&self, affine: &Affine3A) -> Aabb3d {
+        // Each world axis is spanned by the matching row of the matrix.
+        let rows = affine.matrix3.transpose();
+        let rows = [rows.x_axis, rows.y_axis, rows.z_axis];
+        let half_size = match self {
+            Self::Sphere => Vec3A::from_array(rows.map(Vec3A::length)) * 0.5,
+            Self::Cylinder => {
+                let radial = Vec3A::from_array(rows.map(|row| row.xz().length()));
+                (radial + affine.matrix3.y_axis.abs()) * 0.5
+            }
+            Self::Box | Self::ConvexHull(_) => return transform_aabb(affine, &self.local_aabb()),
+        };
+        Aabb3d {
+            min: affine.translation - half_size,
+            max: affine.translation + half_size,
+        }
+    }
+}
+
+/// The convex hull of a small set of points, in a flow's local space.
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct ConvexHull {
+    points: Vec<Vec3>,
+    /// Outward face normals and their distances from the origin.
+    planes: Vec<(Vec3, f32)>,
+}
+
+impl ConvexHull {
+    /// Builds the hull of `points`, which should lie inside the unit cube.
+    ///
+    /// The faces are found by brute force, so this is meant for hulls of a few dozen points,
+    /// built once when the flow is authored. Hulls of fewer than four points, or of coplanar
+    /// ones, have no interior and contain nothing.
+    pub fn new(points: Vec<Vec3>) -> Self {
+        const EPSILON: f32 = 1e-5;
+        let mut planes: Vec<(Vec3, f32)> = Vec::new();
+        let mut solid = false;
+        for (i, a) in points.iter().enumerate() {
+            for (j, b) in points.iter().enumerate().skip(i + 1) {
+                for c in points.iter().skip(j + 1) {
+                    let Some(normal) = (b - a).cross(c - a).try_normalize() else {
+                        continue;
+                    };
+                    let distance = normal.dot(*a);
+                    let (mut above, mut below) = (false, false);
+                    for point in &points {
+                        let side = normal.dot(*point) - distance;
+                        above |= side > EPSILON;
+                        below |= side < -EPSILON;
+                    }
+                    let plane = match (above, below) {
+                        (false, _) => (normal, distance),
+                        (true, false) => (-normal, -distance),
+                        (true, true) => continue,
+                    };
+                    solid |= above || below;
+                    let duplicate = planes.iter().any(|(n, d)| {
+                        n.abs_diff_eq(plane.0, EPSILON) && (d - plane.1).abs() < EPSILON
+                    });
+                    if !duplicate {
+                        planes.push(plane);
+                    }
+                }
+            }
+        }
+        if !solid {
+            planes.clear();
+        }
+        Self { points, planes }
+    }
+
+    pub fn points(&self) -> &[Vec3] {
+        &self.points
+    }
+
+    pub fn contains(&self, local: Vec3) -> bool {
+        !self.planes.is_empty()
+            && self
+                .planes
+                .iter()
+                .all(|(normal, distance)| normal.dot(local) <= *distance)
+    }
+
+    pub fn aabb(&self) -> Aabb3d {
+        if self.points.is_empty() {
+            return Aabb3d::new(Vec3::ZERO, Vec3::ZERO);
+        }
+        let (min, max) = self.points.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), point| (min.min(*point), max.max(*point)),
+        );
+        Aabb3d {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::{PI, TAU};
+
+    use bevy_math::{EulerRot, Quat};
+    use bevy_transform::components::Transform;
+
+    use super::*;
+    use crate::{
+        measure::WindVelocity,
+        test_utils::{FlowWorldBuilder, measured},
+    };
+
+    #[test]
+    fn volumes_contain_their_shapes() {
+        let corner = Vec3::splat(0.45);
+        assert!(FlowVolume::Box.contains(corner));
+        assert!(!FlowVolume::Box.contains(Vec3::X * 0.51));
+        assert!(!FlowVolume::Sphere.contains(corner));
+        assert!(FlowVolume::Sphere.contains(Vec3::Y * 0.5));
+        assert!(FlowVolume::Cylinder.contains(Vec3::new(0.3, 0.5, 0.3)));
+        assert!(!FlowVolume::Cylinder.contains(Vec3::new(0.4, 0.0, 0.4)));
+
+        let tetrahedron = FlowVolume::ConvexHull(ConvexHull::new(vec![
+            Vec3::ZERO,
+            Vec3::X * 0.5,
+            Vec3::Y * 0.5,
+            Vec3::Z * 0.5,
+        ]));
+        assert!(tetrahedron.contains(Vec3::splat(0.1)));
+        assert!(!tetrahedron.contains(Vec3::splat(0.2)));
+        assert!(!tetrahedron.contains(Vec3::splat(-0.01)));
+        assert_eq!(
+            tetrahedron.local_aabb(),
+            Aabb3d::new(Vec3::splat(0.25), Vec3::splat(0.25))
+        );
+    }
+
+    #[test]
+    fn flows_only_blow_inside_their_volume() {
+        let mut world = FlowWorldBuilder::default();
+        let flow = world.uniform_flow(Vec3::X * 3.0, Transform::from_scale(Vec3::splat(2.0)));
+        world
+            .world_mut()
+            .entity_mut(flow)
+            .insert(FlowVolume::Sphere);
+        let inside = world.vane(Vec3::X * 0.9);
+        let corner = world.vane(Vec3::new(0.9, 0.9, 0.0));
+        for vane in [inside, corner] {
+            world.world_mut().entity_mut(vane).insert(WindVelocity);
+        }
+        world.step(2);
+
+        let wind = measured::<WindVelocity>(world.world(), inside);
+        assert!(wind.abs_diff_eq(Vec3::X * 3.0, 1e-4), "inside read {wind}");
+        assert_eq!(measured::<WindVelocity>(world.world(), corner), Vec3::ZERO);
+    }
+
+    #[test]
+    fn degenerate_hulls_contain_nothing() {
+        let square = ConvexHull::new(vec![
+            Vec3::ZERO,
+            Vec3::X * 0.5,
+            Vec3::Y * 0.5,
+            Vec3::new(0.5, 0.5, 0.0),
+        ]);
+        let segment = ConvexHull::new(vec![Vec3::ZERO, Vec3::X * 0.5]);
+        for hull in [square, segment] {
+            for point in [Vec3::splat(0.1), Vec3::new(0.1, 0.1, 0.0), Vec3::NEG_Z] {
+                assert!(!hull.contains(point), "{hull:?} contains {point}");
+            }
+        }
+    }
+
+    #[test]
+    fn round_volumes_bound_tightly() {
+        let affine = Transform::from_xyz(1.0, 2.0, 3.0)
+            .with_rotation(Quat::from_euler(EulerRot::XYZ, 0.3, 0.8, 1.1))
+            .with_scale(Vec3::new(2.0, 5.0, 1.0))
+            .compute_affine();
+        // Points on the surface of each volume.
+        let sphere = (0..64).flat_map(|i| {
+            (0..64).map(move |j| {
+                let (theta, phi) = (i as f32 / 64.0 * TAU, j as f32 / 63.0 * PI);
+                Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin()) * 0.5
+            })
+        });
+        let cylinder = (0..256).flat_map(|i| {
+            let theta = i as f32 / 256.0 * TAU;
+            [-0.5, 0.5].map(|y| Vec3::new(theta.cos() * 0.5, y, theta.sin() * 0.5))
+        });
+        let surfaces: [(FlowVolume, Vec<Vec3>); 2] = [
+            (FlowVolume::Sphere, sphere.collect()),
+            (FlowVolume::Cylinder, cylinder.collect()),
+        ];
+        for (volume, surface) in surfaces {
+            let points = surface.iter().map(|&point| affine.transform_point3(point));
+            let (min, max) = points.fold(
+                (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                |(min, max), point| (min.min(point), max.max(point)),
+            );
+            let aabb = volume.world_aabb(&affine);
+            assert!(
+                Vec3::from(aabb.min).abs_diff_eq(min, 0.02)
+                    && Vec3::from(aabb.max).abs_diff_eq(max, 0.02),
+                "{volume:?} is bounded by {aabb:?}, its surface by {min}..{max}"
+            );
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use core::time::Duration;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::{Commands, IntoScheduleConfigs, Res, ResMut, Resource};
+use bevy_math::Dir3;
+use bevy_time::Time;
+
+use crate::{
+    ambient::AmbientFlow,
+    flow::{AIR_DENSITY, FlowLayerInfluence, FlowLayers, FlowSystems, FlowVector, beaufort_speed},
+    units::VaneUnits,
+};
+
+/// The prevailing wind at one moment of the weather.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeatherState {
+    /// The direction the wind blows towards.
+    pub direction: Dir3,
+    /// Wind speed in meters per second, converted with the [`VaneUnits`] when applied.
+    pub speed: f32,
+    /// Multipliers for the flows on each layer, such as quieting foliage during calm weather.
+    pub layer_influence: FlowLayerInfluence,
+}
+
+impl WeatherState {
+    pub fn new(direction: Dir3, speed: f32) -> Self {
+        Self {
+            direction,
+            speed,
+            layer_influence: FlowLayerInfluence::default(),
+        }
+    }
+
+    /// A wind of Beaufort force `level` towards `direction`.
+    pub fn beaufort(level: f32, direction: Dir3) -> Self {
+        Self::new(direction, beaufort_speed(level))
+    }
+
+    pub fn with_layer_influence(mut self, layer: u8, multiplier: f32) -> Self {
+        self.layer_influence.set(layer, multiplier);
+        self
+    }
+
+    /// Interpolates towards `other`, turning the direction along the shortest arc.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            direction: self.direction.slerp(other.direction, t),
+            speed: self.speed + (other.speed - self.speed) * t,
+            layer_influence: self.layer_influence.lerp(&other.layer_influence, t),
+        }
+    }
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        Self::new(Dir3::X, 0.0)
+    }
+}
+
+struct WeatherTransition {
+    from: WeatherState,
+    to: WeatherState,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+/// High-level control over the wind, above individual flows and fields.
+///
+/// The [`WeatherPlugin`] drives the [`AmbientFlow`] and the [`FlowLayerInfluence`] from the
+/// current state, which moves smoothly towards the targets set with
+/// [`transition_to`](Self::transition_to) as virtual time passes.
+#[derive(Resource)]
+pub struct WeatherWind {
+    /// The layers the ambient wind blows on. Defaults to all of them, like [`AmbientFlow`].
+    pub layers: FlowLayers,
+    current: WeatherState,
+    transition: Option<WeatherTransition>,
+}
+
+impl WeatherWind {
+    pub fn new(state: WeatherState) -> Self {
+        Self {
+            layers: FlowLayers::all(),
+            current: state,
+            transition: None,
+        }
+    }
+
+    pub fn with_layers(mut self, layers: FlowLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// The state of the weather right now, partway through any transition.
+    pub fn current(&self) -> &WeatherState {
+        &self.current
+    }
+
+    /// The state the weather is heading towards.
+    pub fn target(&self) -> &WeatherState {
+        self.transition
+            .as_ref()
+            .map_or(&self.current, |transition| &transition.to)
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Moves from the current state to `target` over `duration`, replacing any transition in
+    /// progress.
+    pub fn transition_to(&mut self, target: WeatherState, duration: Duration) {
+        self.transition = Some(WeatherTransition {
+            from: self.current.clone(),
+            to: target,
+            duration,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Jumps straight to `state`, cancelling any transition.
+    pub fn set(&mut self, state: WeatherState) {
+        self.current = state;
+        self.transition = None;
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        let Some(transition) = &mut self.transition else {
+            return;
+        };
+        transition.elapsed += delta;
+        if transition.elapsed >= transition.duration {
+            let transition = self.transition.take().unwrap();
+            self.current = transition.to;
+        } else {
+            let t = transition.elapsed.as_secs_f32() / transition.duration.as_secs_f32();
+            let t = t * t * (3.0 - 2.0 * t);
+            self.current = transition.from.lerp(&transition.to, t);
+        }
+    }
+}
+
+impl Default for WeatherWind {
+    fn default() -> Self {
+        Self::new(WeatherState::default())
+    }
+}
+
+/// Drives the wind from [`WeatherWind`], which it adds if missing.
+///
+/// It owns the [`AmbientFlow`] and [`FlowLayerInfluence`] resources and overwrites them every
+/// frame. Not part of [`VanePlugins`](crate::VanePlugins).
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherWind>()
+            .init_resource::<VaneUnits>()
+            .add_systems(PostUpdate, update_weather.before(FlowSystems));
+    }
+}
+
+fn update_weather(
+    time: Res<Time>,
+    units: Res<VaneUnits>,
+    mut weather: ResMut<WeatherWind>,
+    mut commands: Commands,
+) {
+    weather.tick(time.delta());
+    let state = weather.current();
+    let velocity = state.direction * units.speed(state.speed);
+    let ambient = AmbientFlow::uniform(FlowVector::from_velocity(velocity, AIR_DENSITY))
+        .with_layers(weather.layers);
+    commands.insert_resource(ambient);
+    commands.insert_resource(state.layer_influence.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use super::*;
+    use crate::{
+        measure::WindVelocity,
+        test_utils::{FlowWorldBuilder, measured},
+    };
+
+    #[test]
+    fn weather_blows_in_app_units() {
+        let mut world = FlowWorldBuilder::default();
+        world
+            .app_mut()
+            .add_plugins(WeatherPlugin)
+            .insert_resource(WeatherWind::new(WeatherState::new(Dir3::X, 2.0)))
+            .insert_resource(VaneUnits::with_length(0.01));
+        let vane = world.vane(Vec3::ZERO);
+        world.world_mut().entity_mut(vane).insert(WindVelocity);
+        world.step(2);
+
+        let wind = measured::<WindVelocity>(world.world(), vane);
+        assert!(
+            wind.abs_diff_eq(Vec3::X * 200.0, 1e-2),
+            "the vane read {wind}"
+        );
+    }
+}